@@ -1,11 +1,25 @@
 use console::style;
 
+use crate::shell::Shell;
+
 #[derive(PartialEq)]
 pub enum Mode {
     X,
     Help,
     Version,
     Query,
+    /// delete installations not used within this many days
+    Gc(u64),
+    /// print the resolved dependency graph, as Graphviz DOT if set
+    Tree {
+        dot: bool,
+    },
+    /// report every malformed `package.yml` in the pantry
+    LintPantry,
+    /// print what the pantry says about projects
+    Info,
+    /// full-text search of the pantry
+    Search,
 }
 
 pub struct Flags {
@@ -16,6 +30,21 @@ pub struct Flags {
     pub shebang: bool,
     pub sync: bool,
     pub chdir: Option<String>,
+    pub lock: bool,
+    pub locked: bool,
+    pub offline: bool,
+    /// projects to choose when more than one provides a program
+    pub prefer: Vec<String>,
+    /// resolve & fetch for this platform instead, eg. `linux/aarch64`
+    pub platform: Option<String>,
+    /// how to write the environment for `pkgx +pkg`
+    pub shell: Option<Shell>,
+    /// write how to undo the environment rather than set it
+    pub restore: bool,
+    /// print what we would change in the environment rather than run anything
+    pub env_diff: bool,
+    /// print what `--gc` would delete rather than delete it
+    pub dry_run: bool,
 }
 
 pub struct Args {
@@ -39,6 +68,15 @@ pub fn parse() -> Args {
     let mut shebang = false;
     let mut sync = false;
     let mut chdir = None;
+    let mut lock = false;
+    let mut locked = false;
+    let mut offline = false;
+    let mut prefer = Vec::new();
+    let mut platform = None;
+    let mut shell = None;
+    let mut restore = false;
+    let mut dry_run = false;
+    let mut env_diff = false;
     let json_latest_v: isize = 2;
 
     let mut args_iter = std::env::args().skip(1);
@@ -72,6 +110,30 @@ pub fn parse() -> Args {
                 "--quiet" => quiet = true,
                 "--query" => mode = Mode::Query,
                 "--sync" => sync = true,
+                "--gc" => mode = Mode::Gc(30),
+                arg if arg.starts_with("--gc=") => match arg[5..].parse() {
+                    Ok(days) => mode = Mode::Gc(days),
+                    Err(_) => panic!("invalid argument {}", arg),
+                },
+                "--dry-run" => dry_run = true,
+                "--tree" => mode = Mode::Tree { dot: false },
+                "--tree=dot" => mode = Mode::Tree { dot: true },
+                "--lint-pantry" => mode = Mode::LintPantry,
+                "--info" => mode = Mode::Info,
+                "--search" => mode = Mode::Search,
+                "--lock" => lock = true,
+                "--locked" => locked = true,
+                "--offline" => offline = true,
+                "--prefer" => prefer.extend(args_iter.next()),
+                arg if arg.starts_with("--prefer=") => prefer.push(arg[9..].to_string()),
+                "--platform" => platform = args_iter.next(),
+                arg if arg.starts_with("--platform=") => platform = Some(arg[11..].to_string()),
+                arg if arg.starts_with("--shell=") => match arg[8..].parse() {
+                    Ok(sh) => shell = Some(sh),
+                    Err(err) => panic!("{}", err),
+                },
+                "--restore" => restore = true,
+                "--env-diff" => env_diff = true,
                 "--shellcode" => {
                     if !silent {
                         eprintln!("{}", style("⨯ migration required").red());
@@ -115,7 +177,15 @@ pub fn parse() -> Args {
                 }
             }
         } else {
-            if mode != Mode::Query {
+            if !matches!(
+                mode,
+                Mode::Query
+                    | Mode::Info
+                    | Mode::Search
+                    | Mode::Gc(_)
+                    | Mode::Tree { .. }
+                    | Mode::LintPantry
+            ) {
                 find_program = !arg.contains('/');
                 collecting_args = true;
             }
@@ -136,6 +206,15 @@ pub fn parse() -> Args {
             version_n_continue,
             sync,
             chdir,
+            lock,
+            locked,
+            offline,
+            prefer,
+            platform,
+            shell,
+            restore,
+            env_diff,
+            dry_run,
         },
     }
 }
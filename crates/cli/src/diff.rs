@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use console::style;
+use libpkgx::{
+    env::Source,
+    platform_case_aware_env_key::{construct_platform_case_aware_env_key, PlatformCaseAwareEnvKey},
+};
+use serde::Serialize;
+
+/// How a variable differs from what we were run with.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Added,
+    /// the old value is still there at the end
+    Prepended,
+    Changed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub key: String,
+    pub change: Change,
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub was: Option<String>,
+    pub sources: Vec<Source>,
+}
+
+/// What `env` adds to or changes in `parent`, sorted by key. Anything no
+/// package is a source of was set by pkgx itself.
+pub fn diff(
+    parent: &HashMap<PlatformCaseAwareEnvKey, String>,
+    env: &HashMap<PlatformCaseAwareEnvKey, String>,
+    sources: &HashMap<PlatformCaseAwareEnvKey, Vec<Source>>,
+) -> Vec<Entry> {
+    let mut rv: Vec<Entry> = env
+        .iter()
+        .filter_map(|(key, value)| {
+            let was = parent.get(key);
+            if was == Some(value) {
+                return None;
+            }
+            let change = match was.filter(|was| !was.is_empty()) {
+                None => Change::Added,
+                Some(was) if value.ends_with(was.as_str()) => Change::Prepended,
+                Some(_) => Change::Changed,
+            };
+            let sources = match sources.get(key) {
+                Some(sources) if !sources.is_empty() => sources.clone(),
+                _ => vec![Source::Pkgx],
+            };
+            Some(Entry {
+                key: key.to_string(),
+                change,
+                value: value.clone(),
+                was: was.cloned(),
+                sources,
+            })
+        })
+        .collect();
+    rv.sort_by(|a, b| a.key.cmp(&b.key));
+    rv
+}
+
+/// Our environment, to `diff` against.
+pub fn parent() -> HashMap<PlatformCaseAwareEnvKey, String> {
+    std::env::vars()
+        .map(|(key, value)| (construct_platform_case_aware_env_key(key), value))
+        .collect()
+}
+
+pub fn print(entries: &[Entry], json: bool) -> Result<(), serde_json::Error> {
+    if json {
+        println!("{}", serde_json::to_string(entries)?);
+        return Ok(());
+    }
+    for entry in entries {
+        match (&entry.change, &entry.was) {
+            (Change::Prepended, Some(was)) => {
+                let prepended = &entry.value[..entry.value.len() - was.len()];
+                println!("^ {}={}${}", entry.key, prepended, entry.key);
+            }
+            (Change::Changed, Some(was)) => {
+                println!(
+                    "~ {}={} {}",
+                    entry.key,
+                    entry.value,
+                    style(format!("(was {})", was)).dim()
+                );
+            }
+            _ => println!("+ {}={}", entry.key, entry.value),
+        }
+        for source in &entry.sources {
+            let source = match source {
+                Source::Path { pkg, suffix, .. } => format!("{} {}", pkg, suffix),
+                Source::Runtime { pkg, line } => format!("{} runtime.env {}", pkg, line),
+                Source::Pkgx => "pkgx".to_string(),
+            };
+            println!("    {}", style(format!("← {}", source)).dim());
+        }
+    }
+    Ok(())
+}
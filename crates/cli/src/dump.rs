@@ -1,20 +1,26 @@
-use std::{collections::HashMap, path::PathBuf, vec};
+use std::{collections::HashMap, path::PathBuf};
 
 use libpkgx::{
-    env::expand_moustaches, pantry_db,
-    platform_case_aware_env_key::construct_platform_case_aware_env_key, types::Installation,
+    config::Config,
+    env::{EnvValue, Paths},
+    moustache, pantry_db,
+    types::Installation,
 };
 use serde::Serialize;
 use serde_json::json;
 
+use crate::shell;
+
 pub fn dump(
     conn: rusqlite::Connection,
     installations: Vec<Installation>,
     flags: &crate::args::Flags,
+    config: &Config,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if let Some(v) = flags.json {
+        let paths = Paths::load(&installations, &conn, config)?;
         if v < 2 {
-            let env = libpkgx::env::map(&installations);
+            let env = libpkgx::env::map(&installations, &paths);
             let mut runtime_env = HashMap::new();
             for pkg in installations.clone() {
                 let pkg_runtime_env =
@@ -32,14 +38,18 @@ pub fn dump(
         } else {
             let mut pkgs: HashMap<String, JsonV2Pkg> = HashMap::new();
             for installation in installations.clone() {
-                let env = libpkgx::env::map(&vec![installation.clone()]);
+                let env = libpkgx::env::map(std::slice::from_ref(&installation), &paths);
                 let project = installation.pkg.project.clone();
 
                 let mut runtime_env = libpkgx::pantry_db::runtime_env_for_project(&project, &conn)?;
 
                 for (installation_key, installation_value) in runtime_env.clone() {
-                    let installation_value =
-                        expand_moustaches(&installation_value, &installation, &installations);
+                    let installation_value = moustache::render(
+                        &installation_value,
+                        &installation,
+                        &installations,
+                        config,
+                    )?;
                     runtime_env.insert(installation_key, installation_value);
                 }
 
@@ -63,28 +73,24 @@ pub fn dump(
             }
 
             let json = json!({
-                "pkgs": pkgs, "env": libpkgx::env::map(&installations)
+                "pkgs": pkgs, "env": libpkgx::env::map(&installations, &paths)
             });
             println!("{}", json);
         }
     } else {
-        let env = libpkgx::env::map(&installations);
-        let env = env
-            .iter()
-            .map(|(k, v)| {
-                (
-                    construct_platform_case_aware_env_key(k.clone()),
-                    v.join(":"),
-                )
-            })
+        let values = libpkgx::env::runtime(&installations, &conn, config)?;
+        let mut values: Vec<(String, EnvValue)> = values
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
             .collect();
-        let env = libpkgx::env::mix_runtime(&env, &installations, &conn)?;
-        for (key, value) in env {
-            println!(
-                "{}=\"{}\"",
-                key,
-                value.replace(&format!(":${}", key), &format!("${{{}:+:${}}}", key, key))
-            );
+        values.sort_by(|a, b| a.0.cmp(&b.0));
+        for (key, value) in values {
+            if flags.restore {
+                let old = std::env::var(&key).ok();
+                println!("{}", shell::restore(flags.shell, &key, old.as_deref()));
+            } else {
+                println!("{}", shell::set(flags.shell, &key, &value));
+            }
         }
     }
     Ok(())
@@ -0,0 +1,58 @@
+use std::{error::Error, path::Path, time::Duration};
+
+use libpkgx::{config::Config, gc, lockfile, lockfile::Lockfile, pantry_db};
+use rusqlite::Connection;
+
+use crate::{
+    args::Flags,
+    resolve::{parse_pkgspec, resolve_project},
+    which::Preferences,
+};
+
+/// Deletes installations that aren’t reachable from any `+pkg`, any lockfile
+/// given as an argument (or `./pkgx.lock`) or anything used within `days`,
+/// or only prints them with `--dry-run`.
+pub async fn gc(
+    args: &[String],
+    plus: &[String],
+    days: u64,
+    flags: &Flags,
+    conn: &Connection,
+    config: &Config,
+) -> Result<(), Box<dyn Error>> {
+    let prefs = &Preferences::load(flags);
+    let mut roots = vec![];
+
+    for pkgspec in plus {
+        let mut pkgspec = parse_pkgspec(pkgspec)?;
+        resolve_project(&mut pkgspec, config, conn, &roots, prefs)?;
+        roots.push(pkgspec.pkgreq(config).await);
+    }
+
+    let mut lockfiles: Vec<&Path> = args.iter().map(Path::new).collect();
+    if lockfiles.is_empty() && Path::new(lockfile::FILENAME).is_file() {
+        lockfiles.push(Path::new(lockfile::FILENAME));
+    }
+    for path in lockfiles {
+        roots.extend(Lockfile::read(path, &config.platform)?.reqs()?);
+    }
+
+    let keep = Duration::from_secs(days * 24 * 60 * 60);
+    let garbage = gc::garbage(&roots, keep, config, |project| {
+        pantry_db::deps_for_project(&project.to_string(), conn)
+    })
+    .await?;
+
+    for installation in garbage {
+        if flags.dry_run {
+            println!("would prune: {}", installation.path.display());
+            continue;
+        }
+        if !flags.silent {
+            println!("pruning: {}", installation.path.display());
+        }
+        gc::remove(&installation, config).await?;
+    }
+
+    Ok(())
+}
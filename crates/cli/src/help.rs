@@ -23,8 +23,14 @@ examples:
   $ pkgx +openssl cargo build
 
 modes:
-  $ pkgx --query bun  # could you run `bun`? (-Q)
-  $ pkgx --help       # hi mom!
+  $ pkgx --query bun   # could you run `bun`? (-Q)
+  $ pkgx --search yaml # which pkgs mention yaml?
+  $ pkgx --gc=7        # delete pkgs unused for a week, keeping ./pkgx.lock
+                       # (--dry-run to only list them)
+  $ pkgx --tree node   # what would `node` bring in? (--tree=dot for Graphviz)
+  $ pkgx --info git    # description, homepage, license, deps…
+  $ pkgx --lint-pantry # report malformed package.yml files
+  $ pkgx --help        # hi mom!
   $ pkgx --version
 
 flags:
@@ -33,6 +39,14 @@ flags:
   -j,  --json=v2    # output JSON (if sensible)
   -C,  --chdir <d>  # change directory first
   --sync            # sync first (note: rarely if ever needed)
+  --lock            # pin the resolved pkgs to ./pkgx.lock
+  --locked          # install & run exactly what ./pkgx.lock pins
+  --offline         # never touch the network (also: PKGX_OFFLINE=1)
+  --prefer <p>      # choose project p when several provide a program
+  --platform <p>    # fetch for eg. linux/aarch64 (into $PKGX_DIR)
+  --shell=<sh>      # write +pkg env for bash|zsh|fish|nu|pwsh|posix
+  --restore         # write how to undo the +pkg env instead
+  --env-diff        # show what would change in the env, don’t run anything
   -v                # print version and continue
 
 more:
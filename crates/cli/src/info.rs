@@ -0,0 +1,63 @@
+use std::error::Error;
+
+use libpkgx::{config::Config, pantry_db};
+use rusqlite::Connection;
+
+use crate::{
+    args::Flags,
+    resolve::{parse_pkgspec, resolve_project},
+    which::Preferences,
+};
+
+/// Prints what the pantry says about each project (or program) asked for.
+pub async fn info(
+    args: &[String],
+    plus: &[String],
+    flags: &Flags,
+    conn: &Connection,
+    config: &Config,
+) -> Result<(), Box<dyn Error>> {
+    let prefs = &Preferences::load(flags);
+    let mut infos = vec![];
+    for arg in plus.iter().chain(args) {
+        let mut pkgspec = parse_pkgspec(arg)?;
+        resolve_project(&mut pkgspec, config, conn, &[], prefs)?;
+        let project = pkgspec.project();
+        match pantry_db::info(&project, conn)? {
+            Some(info) => infos.push(info),
+            None => return Err(format!("{} not found", project).into()),
+        }
+    }
+
+    if flags.json.is_some() {
+        println!("{}", serde_json::to_string_pretty(&infos)?);
+        return Ok(());
+    }
+
+    for (n, info) in infos.iter().enumerate() {
+        if n > 0 {
+            println!();
+        }
+        println!("{}", info.project);
+        let field = |key: &str, value: String| {
+            if !value.is_empty() {
+                println!("  {:<20}{}", format!("{}:", key), value);
+            }
+        };
+        field("name", info.display_name.clone().unwrap_or_default());
+        field("description", info.description.clone().unwrap_or_default());
+        field("homepage", info.homepage.clone().unwrap_or_default());
+        field("license", info.license.clone().unwrap_or_default());
+        field("platforms", info.platforms.join(", "));
+        field("programs", info.programs.join(", "));
+        field("dependencies", info.dependencies.join(", "));
+        field("build dependencies", info.build_dependencies.join(", "));
+        field("companions", info.companions.join(", "));
+        // only worth mentioning if it isn’t the usual pantry
+        if let Some(pantry) = info.pantry.as_ref().filter(|p| **p != config.pantry_dir) {
+            field("pantry", pantry.display().to_string());
+        }
+    }
+
+    Ok(())
+}
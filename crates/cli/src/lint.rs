@@ -0,0 +1,28 @@
+use std::{collections::HashSet, error::Error};
+
+use libpkgx::{config::Config, pantry};
+
+use crate::args::Flags;
+
+/// Prints every `package.yml` the pantry db can’t fully make sense of and why.
+pub fn lint(flags: &Flags, config: &Config) -> Result<(), Box<dyn Error>> {
+    let issues = pantry::lint(config);
+
+    if flags.json.is_some() {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({ "issues": issues }))?
+        );
+    } else {
+        for issue in &issues {
+            println!("{}", issue);
+        }
+    }
+
+    let files: HashSet<_> = issues.iter().map(|issue| &issue.path).collect();
+    match files.len() {
+        0 => Ok(()),
+        1 => Err("1 malformed package.yml")?,
+        n => Err(format!("{} malformed package.yml files", n))?,
+    }
+}
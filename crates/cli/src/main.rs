@@ -1,28 +1,56 @@
 mod args;
+mod diff;
 mod dump;
 mod execve;
+mod gc;
 mod help;
+mod info;
+mod lint;
 mod query;
 mod resolve;
+mod search;
+mod shell;
 mod spinner;
 #[cfg(test)]
 mod tests;
+mod tree;
 mod which;
 mod x;
 
+use console::style;
 use execve::execve;
-use libpkgx::{config::Config, sync};
+use libpkgx::{
+    config::Config,
+    lockfile::{self, Lockfile},
+    sync,
+    types::Platform,
+};
 use spinner::Spinner;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+async fn main() {
+    let args = args::parse();
+    let silent = args.flags.silent;
+    let json = args.flags.json.is_some();
+    if let Err(err) = run(args).await {
+        if silent {
+        } else if json {
+            eprintln!("{}", error_json(err.as_ref()));
+        } else {
+            eprintln!("error: {}", err);
+        }
+        std::process::exit(exit_code(err.as_ref()));
+    }
+}
+
+async fn run(args: args::Args) -> Result<(), Box<dyn std::error::Error>> {
     let args::Args {
         plus,
         mut args,
         mode,
         flags,
         find_program,
-    } = args::parse();
+    } = args;
 
     if let Some(dir) = &flags.chdir {
         std::env::set_current_dir(dir)?;
@@ -45,31 +73,111 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             let (conn, _, config, _) = setup(&flags).await?;
             query::query(&args, &flags, &conn, &config).await
         }
+        args::Mode::Search => {
+            let (conn, _, _, _) = setup(&flags).await?;
+            search::search(&args, &flags, &conn)
+        }
+        args::Mode::Info => {
+            let (conn, _, config, _) = setup(&flags).await?;
+            info::info(&args, &plus, &flags, &conn, &config).await
+        }
+        args::Mode::Gc(days) => {
+            let (conn, _, config, _) = setup(&flags).await?;
+            gc::gc(&args, &plus, days, &flags, &conn, &config).await
+        }
+        args::Mode::Tree { dot } => {
+            let (conn, _, config, spinner) = setup(&flags).await?;
+            spinner.finish_and_clear();
+            tree::tree(&args, &plus, dot, &flags, &conn, &config).await
+        }
+        args::Mode::LintPantry => {
+            let (_, _, config, _) = setup(&flags).await?;
+            lint::lint(&flags, &config)
+        }
         args::Mode::X => {
             let (mut conn, did_sync, config, mut spinner) = setup(&flags).await?;
-            let (installations, graph) = resolve::resolve(
-                &mut args,
-                &plus,
-                find_program,
-                &config,
-                &mut conn,
-                did_sync,
-                &mut spinner,
-            )
-            .await?;
+            if !args.is_empty() && config.platform != Platform::current() {
+                return Err(format!(
+                    "{} programs can’t run here, use `+pkg` to only fetch them",
+                    config.platform
+                )
+                .into());
+            }
+            let prefs = which::Preferences::load(&flags);
+            let (installations, graph) = if flags.locked {
+                resolve::resolve_locked(
+                    &mut args,
+                    &plus,
+                    find_program,
+                    &config,
+                    &conn,
+                    &prefs,
+                    &mut spinner,
+                )
+                .await?
+            } else {
+                resolve::resolve(
+                    &mut args,
+                    &plus,
+                    find_program,
+                    &config,
+                    &mut conn,
+                    did_sync,
+                    &prefs,
+                    &mut spinner,
+                )
+                .await?
+            };
+
+            if flags.lock {
+                spinner.set_message(&format!("writing {}…", lockfile::FILENAME));
+                Lockfile::from_installations(&installations, &config)
+                    .await?
+                    .write(std::path::Path::new(lockfile::FILENAME))?;
+            }
+
+            libpkgx::gc::mark_used(&installations);
 
             if !args.is_empty() {
-                let env = libpkgx::env::map(&installations);
-                let (cmd, args, env) =
-                    x::exec(find_program, args, installations, env, flags, conn, graph).await?;
+                let paths = libpkgx::env::Paths::load(&installations, &conn, &config)?;
+                let env = libpkgx::env::map(&installations, &paths);
+                let sources = if flags.env_diff {
+                    Some(libpkgx::env::sources(&installations, &conn, &config)?)
+                } else {
+                    None
+                };
+                let json = flags.json.is_some();
+                let (cmd, args, env) = x::exec(
+                    find_program,
+                    args,
+                    installations,
+                    env,
+                    flags,
+                    conn,
+                    &config,
+                    graph,
+                )
+                .await?;
                 spinner.finish_and_clear();
+                if let Some(sources) = sources {
+                    diff::print(&diff::diff(&diff::parent(), &env, &sources), json)?;
+                    return Ok(());
+                }
                 execve(cmd, args, env)?;
                 Ok(())
             } else if !plus.is_empty() {
                 spinner.finish_and_clear();
-                dump::dump(conn, installations, &flags)?;
+                if flags.env_diff {
+                    let values = libpkgx::env::runtime(&installations, &conn, &config)?;
+                    let env = libpkgx::env::mix(&values);
+                    let sources = libpkgx::env::sources(&installations, &conn, &config)?;
+                    let entries = diff::diff(&diff::parent(), &env, &sources);
+                    diff::print(&entries, flags.json.is_some())?;
+                } else {
+                    dump::dump(conn, installations, &flags, &config)?;
+                }
                 Ok(())
-            } else if flags.version_n_continue || flags.sync {
+            } else if flags.version_n_continue || flags.sync || flags.lock {
                 Ok(())
             } else {
                 spinner.finish_and_clear();
@@ -83,14 +191,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 async fn setup(
     flags: &args::Flags,
 ) -> Result<(rusqlite::Connection, bool, Config, Spinner), Box<dyn std::error::Error>> {
-    let config = Config::new()?;
+    let mut config = Config::new()?;
+    if !flags.silent {
+        for warning in &config.warnings {
+            eprintln!("{}", style(format!("warning: {}", warning)).yellow());
+        }
+    }
+    if flags.offline {
+        config.offline = true;
+    }
+    if let Some(platform) = &flags.platform {
+        config.cross(platform.parse()?)?;
+    }
 
-    std::fs::create_dir_all(config.pantry_db_file.parent().unwrap())?;
-    let mut conn = rusqlite::Connection::open(&config.pantry_db_file)?;
+    let mut conn = libpkgx::pantry_db::open(&config.pantry_db_file)?;
 
     let mut spinner = Spinner::new(flags.quiet, flags.silent);
 
-    let did_sync = if flags.sync || sync::should(&config)? {
+    let did_sync = if flags.sync || sync::should(&config, &conn)? {
         spinner.set_message("syncing pkg-db…");
         sync::ensure(&config, &mut conn).await?;
         true
@@ -101,6 +219,49 @@ async fn setup(
     Ok((conn, did_sync, config, spinner))
 }
 
+fn error_json(err: &(dyn std::error::Error + 'static)) -> serde_json::Value {
+    let mut json = serde_json::json!({ "error": err.to_string() });
+    if let Some(libpkgx::Error::Conflict(conflict)) = err.downcast_ref::<libpkgx::Error>() {
+        json["conflict"] = serde_json::json!(conflict);
+    }
+    match err.downcast_ref::<which::WhichError>() {
+        Some(which::WhichError::CmdNotFound(_, suggestions)) => {
+            json["suggestions"] = serde_json::json!(suggestions);
+        }
+        Some(which::WhichError::MultipleProjects(_, projects)) => {
+            json["candidates"] = serde_json::json!(projects);
+        }
+        _ => {}
+    }
+    json
+}
+
+// 2 is usage, so errors we can classify start at 3
+fn exit_code(err: &(dyn std::error::Error + 'static)) -> i32 {
+    if let Some(err) = err.downcast_ref::<libpkgx::Error>() {
+        match err {
+            libpkgx::Error::NotFound(_)
+            | libpkgx::Error::Unsupported { .. }
+            | libpkgx::Error::ProgramNotFound(_) => 3,
+            libpkgx::Error::Conflict(_) => 4,
+            libpkgx::Error::Network(_) => 5,
+            libpkgx::Error::Integrity { .. } => 6,
+            libpkgx::Error::Db(_) => 7,
+            libpkgx::Error::Locked(_) => 8,
+            libpkgx::Error::Offline(_) => 9,
+            _ => 1,
+        }
+    } else if let Some(err) = err.downcast_ref::<which::WhichError>() {
+        match err {
+            which::WhichError::CmdNotFound(..) => 3,
+            which::WhichError::MultipleProjects(..) => 1,
+            which::WhichError::DbError(err) => exit_code(err),
+        }
+    } else {
+        1
+    }
+}
+
 fn get_version_string(json: bool) -> String {
     if !json {
         format!("pkgx {}", env!("CARGO_PKG_VERSION"))
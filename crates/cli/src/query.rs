@@ -1,4 +1,4 @@
-use std::error::Error;
+use std::{collections::HashMap, error::Error, path::PathBuf};
 
 use libpkgx::{config::Config, inventory, pantry_db};
 use rusqlite::{params, Connection};
@@ -13,6 +13,10 @@ use crate::{
 struct QueryResult {
     project: String,
     programs: Vec<String>,
+    /// the pantry its `package.yml` is from
+    pantry: Option<PathBuf>,
+    /// whether it runs on this platform
+    supported: bool,
 }
 
 pub async fn query(
@@ -35,16 +39,31 @@ pub async fn query(
             while let Some(row) = rows.next()? {
                 let project: String = row.get(0)?;
                 let programs = get_programs(conn, &project)?;
-                results.push(QueryResult { project, programs });
+                let pantry = pantry_db::pantry_for_project(&project, conn)?;
+                let supported = pantry_db::supported(&project, &config.platform, conn)?;
+                results.push(QueryResult {
+                    project,
+                    programs,
+                    pantry,
+                    supported,
+                });
             }
             println!("{}", serde_json::to_string_pretty(&results)?);
-        // if not, just list all programs
+        // if not, just list all programs we can run
         } else {
-            let mut stmt = conn.prepare("SELECT program FROM provides")?;
+            let mut stmt = conn.prepare("SELECT program, project FROM provides")?;
             let mut rows = stmt.query(params![])?;
+            let mut supported = HashMap::new();
             while let Some(row) = rows.next()? {
                 let program: String = row.get(0)?;
-                println!("{}", program);
+                let project: String = row.get(1)?;
+                if !supported.contains_key(&project) {
+                    let is = pantry_db::supported(&project, &config.platform, conn)?;
+                    supported.insert(project.clone(), is);
+                }
+                if supported[&project] {
+                    println!("{}", program);
+                }
             }
         }
         return Ok(());
@@ -91,7 +110,7 @@ pub async fn query(
             if silent {
                 std::process::exit(1);
             }
-            println!("{} not found", arg);
+            println!("{}", not_found(arg, conn));
             fail = true;
             continue;
         }
@@ -117,13 +136,38 @@ pub async fn query(
         if is_json {
             for project in &projects {
                 let programs = get_programs(conn, project)?;
+                let pantry = pantry_db::pantry_for_project(project, conn)?;
+                let supported = pantry_db::supported(project, &config.platform, conn)?;
+                fail |= !supported;
                 results.push(QueryResult {
                     project: project.clone(),
                     programs,
+                    pantry,
+                    supported,
                 });
             }
         } else if !silent {
-            println!("{}", projects.join(", "));
+            let mut described = vec![];
+            for project in &projects {
+                let mut notes = vec![];
+                // only worth mentioning if it isn’t the usual pantry
+                match pantry_db::pantry_for_project(project, conn)? {
+                    Some(pantry) if pantry != config.pantry_dir => {
+                        notes.push(pantry.display().to_string())
+                    }
+                    _ => {}
+                }
+                if !pantry_db::supported(project, &config.platform, conn)? {
+                    notes.push(format!("not available for {}", config.platform));
+                    fail = true;
+                }
+                if notes.is_empty() {
+                    described.push(project.clone());
+                } else {
+                    described.push(format!("{} ({})", project, notes.join(", ")));
+                }
+            }
+            println!("{}", described.join(", "));
         }
     }
 
@@ -150,12 +194,25 @@ fn resolve_project(input: &str, conn: &Connection) -> Result<String, Box<dyn Err
                     return Ok(input.to_string());
                 }
             }
-            Err(format!("{} not found", input).into())
+            Err(not_found(input, conn).into())
         }
         _ => Err(format!("{} is ambiguous: {}", input, projects.join(", ")).into()),
     }
 }
 
+fn not_found(input: &str, conn: &Connection) -> String {
+    match pantry_db::suggestions(input, conn) {
+        Ok(suggestions) if !suggestions.is_empty() => {
+            format!(
+                "{} not found, did you mean: {}?",
+                input,
+                suggestions.join(", ")
+            )
+        }
+        _ => format!("{} not found", input),
+    }
+}
+
 fn get_programs(conn: &Connection, project: &str) -> Result<Vec<String>, Box<dyn Error>> {
     let mut stmt =
         conn.prepare("SELECT program FROM provides WHERE project = ? ORDER BY program")?;
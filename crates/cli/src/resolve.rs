@@ -1,20 +1,22 @@
-use std::env;
+use std::{env, path::Path};
 
 use libpkgx::{
     config::Config,
-    hydrate::hydrate,
-    install_multi::install_multi,
+    hydrate::Policy,
+    install_multi::{install_multi, install_multi_locked},
+    lockfile::{self, Lockfile},
     pantry_db, sync,
-    types::{Installation, PackageReq},
+    types::{Installation, Package, PackageReq},
     VersionRange,
 };
 use rusqlite::Connection;
 
 use crate::{
     spinner::Spinner,
-    which::{which, WhichError},
+    which::{which, Preferences, WhichError},
 };
 
+#[allow(clippy::too_many_arguments)]
 pub async fn resolve(
     args: &mut [String],
     plus: &[String],
@@ -22,6 +24,7 @@ pub async fn resolve(
     config: &Config,
     conn: &mut Connection,
     did_sync: bool,
+    prefs: &Preferences,
     spinner: &mut Spinner,
 ) -> std::result::Result<(Vec<Installation>, Vec<PackageReq>), Box<dyn std::error::Error>> {
     spinner.set_message("resolving pkg graph…");
@@ -30,17 +33,8 @@ pub async fn resolve(
 
     for pkgspec in plus {
         let mut pkgspec = parse_pkgspec(pkgspec)?;
-
-        if !config
-            .pantry_dir
-            .join("projects")
-            .join(pkgspec.project())
-            .is_dir()
-        {
-            let project = which(&pkgspec.project(), conn, &pkgs).await?;
-            pkgspec.set_project(project);
-        }
-
+        // we may need to ask which project they mean
+        spinner.suspend(|| resolve_project(&mut pkgspec, config, conn, &pkgs, prefs))?;
         pkgs.push(pkgspec.pkgreq(config).await);
     }
 
@@ -50,15 +44,15 @@ pub async fn resolve(
 
         args[0] = cmd.clone(); // invoke eg. `node` rather than eg. `node@20`
 
-        let project = match which(&cmd, conn, &pkgs).await {
-            Err(WhichError::CmdNotFound(cmd)) => {
-                if !did_sync {
+        let project = match spinner.suspend(|| which(&cmd, conn, &pkgs, prefs)) {
+            Err(WhichError::CmdNotFound(cmd, suggestions)) => {
+                if !did_sync && !config.offline {
                     spinner.set_message(&format!("{} not found, syncing…", cmd));
                     sync::update(config, conn).await?; // cmd not found ∴ sync in case it is new
                     spinner.set_message("resolving pkg graph…");
-                    which(&cmd, conn, &pkgs).await
+                    spinner.suspend(|| which(&cmd, conn, &pkgs, prefs))
                 } else {
-                    Err(WhichError::CmdNotFound(cmd))
+                    Err(WhichError::CmdNotFound(cmd, suggestions))
                 }
             }
             Err(err) => Err(err),
@@ -80,9 +74,15 @@ pub async fn resolve(
 
     pkgs.extend(companions);
 
-    let graph = hydrate(&pkgs, |project| pantry_db::deps_for_project(&project, conn)).await?;
+    for pkg in &pkgs {
+        pantry_db::ensure_supported(&pkg.project, &config.platform, conn)?;
+    }
 
-    let resolution = libpkgx::resolve::resolve(&graph, config).await?;
+    let resolution = libpkgx::resolve::solve(&pkgs, &Policy::default(), config, |pkg| {
+        deps(pkg, config, conn)
+    })
+    .await?;
+    let graph = resolution.graph;
 
     let mut installations = resolution.installed;
     if !resolution.pending.is_empty() {
@@ -96,13 +96,122 @@ pub async fn resolve(
     Ok((installations, graph))
 }
 
+/// Installs exactly what `pkgx.lock` pins without consulting the inventory,
+/// erroring if the lockfile doesn’t satisfy what was asked for.
+pub async fn resolve_locked(
+    args: &mut [String],
+    plus: &[String],
+    find_program: bool,
+    config: &Config,
+    conn: &Connection,
+    prefs: &Preferences,
+    spinner: &mut Spinner,
+) -> std::result::Result<(Vec<Installation>, Vec<PackageReq>), Box<dyn std::error::Error>> {
+    spinner.set_message(&format!("reading {}…", lockfile::FILENAME));
+
+    let lockfile = Lockfile::read(Path::new(lockfile::FILENAME), &config.platform)?;
+    // what it pins is preferred when finding projects
+    let pinned = lockfile.reqs()?;
+
+    let mut pkgspecs = vec![];
+    for pkgspec in plus {
+        let mut pkgspec = parse_pkgspec(pkgspec)?;
+        spinner.suspend(|| resolve_project(&mut pkgspec, config, conn, &pinned, prefs))?;
+        pkgspecs.push(pkgspec);
+    }
+
+    if find_program {
+        let mut pkgspec = parse_pkgspec(&args[0])?;
+        let cmd = pkgspec.project();
+        args[0] = cmd.clone();
+        pkgspec.set_project(spinner.suspend(|| which(&cmd, conn, &pinned, prefs))?);
+        pkgspecs.push(pkgspec);
+    }
+
+    let mut projects = vec![];
+    for pkgspec in pkgspecs {
+        let project = pkgspec.project();
+        let locked = lockfile.get(&project).ok_or_else(|| {
+            format!(
+                "{} is not in {}, regenerate it with --lock",
+                project,
+                lockfile::FILENAME
+            )
+        })?;
+        if let Pkgspec::Req(req) = pkgspec {
+            if !req.constraint.satisfies(&locked.version) {
+                return Err(format!(
+                    "{} pins {}={} which doesn’t satisfy {}",
+                    lockfile::FILENAME,
+                    locked.project,
+                    locked.version,
+                    req
+                )
+                .into());
+            }
+        }
+        projects.push(project);
+    }
+
+    // only what was asked for and what that needs
+    let lockfile = lockfile.subset(&projects, |project| {
+        pantry_db::deps_for_project(&project.to_string(), conn)
+    })?;
+    let graph = lockfile.reqs()?;
+
+    let (mut installations, pending) = lockfile.partition(config);
+    if !pending.is_empty() {
+        if env::var("PKGX_NO_INSTALL").is_ok() {
+            return Err("PKGX_NO_INSTALL is set, refusing to install pending packages")?;
+        }
+        let installed = install_multi_locked(&pending, config, spinner.arc()).await?;
+        installations.extend(installed);
+    }
+
+    Ok((installations, graph))
+}
+
+/// The pantry’s dependencies of `pkg`, failing before we look for versions of
+/// any that can’t run on the platform we are resolving for.
+pub fn deps(
+    pkg: &Package,
+    config: &Config,
+    conn: &Connection,
+) -> Result<Vec<PackageReq>, libpkgx::Error> {
+    let deps = pantry_db::deps_for_project(&pkg.project, conn)?;
+    for dep in &deps {
+        pantry_db::ensure_supported(&dep.project, &config.platform, conn)?;
+    }
+    Ok(deps)
+}
+
+// `+node` is a program rather than a project, so find the project that provides it
+pub fn resolve_project(
+    pkgspec: &mut Pkgspec,
+    config: &Config,
+    conn: &Connection,
+    pkgs: &[PackageReq],
+    prefs: &Preferences,
+) -> Result<(), WhichError> {
+    let project = pkgspec.project();
+    if !config
+        .pantries()
+        .iter()
+        .any(|pantry| pantry.join("projects").join(&project).is_dir())
+    {
+        let project = which(&project, conn, pkgs, prefs)?;
+        pkgspec.set_project(project);
+    }
+    Ok(())
+}
+
 pub enum Pkgspec {
     Req(PackageReq),
     Latest(String),
 }
 
 impl Pkgspec {
-    fn project(&self) -> String {
+    pub fn project(&self) -> String {
         match self {
             Pkgspec::Req(req) => req.project.clone(),
             Pkgspec::Latest(project) => project.clone(),
@@ -128,7 +237,7 @@ impl Pkgspec {
         }
     }
 
-    async fn pkgreq(&self, config: &Config) -> PackageReq {
+    pub async fn pkgreq(&self, config: &Config) -> PackageReq {
         let project = self.project();
         let constraint = self.constraint(config).await;
         PackageReq {
@@ -0,0 +1,44 @@
+use std::error::Error;
+
+use libpkgx::pantry_db;
+use rusqlite::Connection;
+
+use crate::args::Flags;
+
+const LIMIT: usize = 25;
+
+/// Prints the projects that best match the search terms.
+pub fn search(args: &[String], flags: &Flags, conn: &Connection) -> Result<(), Box<dyn Error>> {
+    let query = args.join(" ");
+    let hits = pantry_db::search(&query, LIMIT, conn)?;
+
+    if flags.json.is_some() {
+        println!("{}", serde_json::to_string_pretty(&hits)?);
+    } else {
+        for hit in &hits {
+            let mut line = hit.project.clone();
+            if !hit.programs.is_empty() {
+                line.push_str(&format!(": {}", hit.programs.join(", ")));
+            }
+            if let Some(description) = hit.description.as_ref().or(hit.display_name.as_ref()) {
+                line.push_str(&format!(" — {}", description));
+            }
+            println!("{}", line);
+        }
+    }
+
+    if hits.is_empty() {
+        let suggestions = pantry_db::suggestions(&query, conn)?;
+        if suggestions.is_empty() {
+            Err(format!("nothing matches {}", query))?
+        } else {
+            Err(format!(
+                "nothing matches {}, did you mean: {}?",
+                query,
+                suggestions.join(", ")
+            ))?
+        }
+    }
+
+    Ok(())
+}
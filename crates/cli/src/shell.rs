@@ -0,0 +1,288 @@
+use std::{fmt, str::FromStr};
+
+use libpkgx::env::{EnvValue, SEP};
+
+/// The shells `--shell=` can write the environment for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Nu,
+    Pwsh,
+    Posix,
+}
+
+impl FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "nu" | "nushell" => Ok(Shell::Nu),
+            "pwsh" | "powershell" => Ok(Shell::Pwsh),
+            "posix" | "sh" => Ok(Shell::Posix),
+            _ => Err(format!(
+                "unknown shell `{}`, one of: bash, zsh, fish, nu, pwsh, posix",
+                s
+            )),
+        }
+    }
+}
+
+/// The lines that apply `value` to `key` in `shell`, or the historic
+/// `pkgx +pkg` output without it.
+pub fn set(shell: Option<Shell>, key: &str, value: &EnvValue) -> String {
+    let Some(shell) = shell else {
+        return historic(key, value);
+    };
+    let mut lines = vec![];
+    if let Some(default) = &value.default {
+        lines.push(match shell {
+            // bash won’t take a `'` in a `${KEY:-…}` so we test instead
+            Shell::Bash | Shell::Zsh | Shell::Posix => format!(
+                "[ -n \"${{{key}:-}}\" ] || export {key}={}",
+                Quoted(default),
+                key = key
+            ),
+            Shell::Fish => format!(
+                "test -n \"${key}\"; or set -gx {key} {}",
+                fish_quote(default),
+                key = key
+            ),
+            Shell::Nu => format!(
+                "$env.{key} = (if ($env.{key}? | is-empty) {{ {} }} else {{ $env.{key} }})",
+                nu_quote(default),
+                key = key
+            ),
+            Shell::Pwsh => format!(
+                "if (-not $env:{key}) {{ $env:{key} = {} }}",
+                pwsh_quote(default),
+                key = key
+            ),
+        });
+    }
+    if value.prepend.is_some() || value.append.is_some() {
+        lines.push(match shell {
+            Shell::Bash | Shell::Zsh | Shell::Posix => {
+                format!("export {}={}", key, posix(key, value))
+            }
+            Shell::Fish => fish(key, value),
+            Shell::Nu => nu(key, value),
+            Shell::Pwsh => pwsh(key, value),
+        });
+    }
+    lines.join("\n")
+}
+
+// one line a key with the values as is, scripts parse this so it mustn’t change
+fn historic(key: &str, value: &EnvValue) -> String {
+    let parent = match &value.default {
+        Some(default) => format!("${{{}:-{}}}", key, default),
+        None => format!("${}", key),
+    };
+    let mut rv = match &value.prepend {
+        Some((text, sep)) if sep == ":" && value.default.is_none() => {
+            format!("{}${{{key}:+:${key}}}", text, key = key)
+        }
+        Some((text, sep)) => format!("{}{}{}", text, sep, parent),
+        None => parent,
+    };
+    if let Some((sep, text)) = &value.append {
+        rv.push_str(&format!("{}{}", sep, text));
+    }
+    format!("{}=\"{}\"", key, rv)
+}
+
+// whether the parts are all separated like PATH so the shell’s list syntax fits
+fn is_path_like(value: &EnvValue) -> bool {
+    let prepend = value.prepend.as_ref().map(|(_, sep)| sep);
+    let append = value.append.as_ref().map(|(sep, _)| sep);
+    prepend.into_iter().chain(append).all(|sep| sep == SEP)
+}
+
+/// A line that puts `key` back to `old`, or unsets it if it wasn’t set.
+pub fn restore(shell: Option<Shell>, key: &str, old: Option<&str>) -> String {
+    let path_like = old.is_some_and(|old| old.contains(SEP)) || key.ends_with("PATH");
+    match (shell, old) {
+        (None, Some(old)) => format!("{}={}", key, Quoted(old)),
+        (Some(Shell::Bash | Shell::Zsh | Shell::Posix), Some(old)) => {
+            format!("export {}={}", key, Quoted(old))
+        }
+        (None | Some(Shell::Bash | Shell::Zsh | Shell::Posix), None) => format!("unset {}", key),
+        (Some(Shell::Fish), Some(old)) if path_like => format!(
+            "set -gx --path {} (string split {} {})",
+            key,
+            fish_quote(SEP),
+            fish_quote(old)
+        ),
+        (Some(Shell::Fish), Some(old)) => format!("set -gx {} {}", key, fish_quote(old)),
+        (Some(Shell::Fish), None) => format!("set -e {}", key),
+        (Some(Shell::Nu), Some(old)) if is_nu_list(key) => {
+            format!("$env.{} = ({} | split row (char esep))", key, nu_quote(old))
+        }
+        (Some(Shell::Nu), Some(old)) => format!("$env.{} = {}", key, nu_quote(old)),
+        (Some(Shell::Nu), None) => format!("hide-env -i {}", key),
+        (Some(Shell::Pwsh), Some(old)) => format!("$env:{} = {}", key, pwsh_quote(old)),
+        (Some(Shell::Pwsh), None) => {
+            format!("Remove-Item Env:{} -ErrorAction SilentlyContinue", key)
+        }
+    }
+}
+
+/// Double quoted for POSIX shells so `$`, `"`, `\` and backticks are literal.
+struct Quoted<'a>(&'a str);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{}\"", posix_escape(self.0))
+    }
+}
+
+fn posix_escape(text: &str) -> String {
+    let mut rv = String::new();
+    for c in text.chars() {
+        if matches!(c, '$' | '"' | '\\' | '`') {
+            rv.push('\\');
+        }
+        rv.push(c);
+    }
+    rv
+}
+
+fn posix(key: &str, value: &EnvValue) -> String {
+    let mut rv = String::new();
+    if let Some((text, sep)) = &value.prepend {
+        rv.push_str(&format!(
+            "{}${{{key}:+{}${{{key}}}}}",
+            posix_escape(text),
+            posix_escape(sep),
+            key = key
+        ));
+    }
+    match &value.append {
+        Some((sep, text)) if value.prepend.is_some() => {
+            rv.push_str(&format!("{}{}", posix_escape(sep), posix_escape(text)))
+        }
+        Some((sep, text)) => rv.push_str(&format!(
+            "${{{key}:+${{{key}}}{}}}{}",
+            posix_escape(sep),
+            posix_escape(text),
+            key = key
+        )),
+        None => {}
+    }
+    format!("\"{}\"", rv)
+}
+
+fn fish_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+fn fish(key: &str, value: &EnvValue) -> String {
+    if is_path_like(value) {
+        // a path variable is a list that is joined with `:` when exported
+        let split = |text: &String| -> Vec<String> { text.split(SEP).map(fish_quote).collect() };
+        let mut words = vec![];
+        if let Some((text, _)) = &value.prepend {
+            words.extend(split(text));
+        }
+        words.push(format!("${}", key));
+        if let Some((_, text)) = &value.append {
+            words.extend(split(text));
+        }
+        return format!("set -gx --path {} {}", key, words.join(" "));
+    }
+    // `$KEY` expands to nothing if it is unset so isn’t joined
+    let mut expr = format!("${}", key);
+    if let Some((text, sep)) = &value.prepend {
+        expr = format!(
+            "(string join {} {} {})",
+            fish_quote(sep),
+            fish_quote(text),
+            expr
+        );
+    }
+    if let Some((sep, text)) = &value.append {
+        expr = format!(
+            "(string join {} {} {})",
+            fish_quote(sep),
+            expr,
+            fish_quote(text)
+        );
+    }
+    format!("set -gx {} {}", key, expr)
+}
+
+fn nu_quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// nushell keeps PATH as a list, everything else is a string
+fn is_nu_list(key: &str) -> bool {
+    key == "PATH" || key == "Path"
+}
+
+fn nu(key: &str, value: &EnvValue) -> String {
+    if is_nu_list(key) && is_path_like(value) {
+        let list = |text: &String| -> String {
+            let paths: Vec<String> = text.split(SEP).map(nu_quote).collect();
+            format!("[{}]", paths.join(", "))
+        };
+        let mut expr = format!("$env.{}? | default [] | split row (char esep)", key);
+        if let Some((text, _)) = &value.prepend {
+            expr = format!("{} | prepend {}", expr, list(text));
+        }
+        if let Some((_, text)) = &value.append {
+            expr = format!("{} | append {}", expr, list(text));
+        }
+        return format!("$env.{} = ({})", key, expr);
+    }
+    // empty parts are dropped so they aren’t separated
+    let mut expr = format!("($env.{}? | default \"\")", key);
+    if let Some((text, sep)) = &value.prepend {
+        expr = format!(
+            "([{}, {}] | where $it != \"\" | str join {})",
+            nu_quote(text),
+            expr,
+            nu_quote(sep)
+        );
+    }
+    if let Some((sep, text)) = &value.append {
+        expr = format!(
+            "([{}, {}] | where $it != \"\" | str join {})",
+            expr,
+            nu_quote(text),
+            nu_quote(sep)
+        );
+    }
+    format!("$env.{} = {}", key, expr)
+}
+
+fn pwsh_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+fn pwsh(key: &str, value: &EnvValue) -> String {
+    // empty parts are dropped so they aren’t separated
+    let mut expr = format!("$env:{}", key);
+    if let Some((text, sep)) = &value.prepend {
+        expr = format!(
+            "((@({}, {}) | Where-Object {{ $_ }}) -join {})",
+            pwsh_quote(text),
+            expr,
+            pwsh_quote(sep)
+        );
+    }
+    if let Some((sep, text)) = &value.append {
+        expr = format!(
+            "((@({}, {}) | Where-Object {{ $_ }}) -join {})",
+            expr,
+            pwsh_quote(text),
+            pwsh_quote(sep)
+        );
+    }
+    format!("$env:{} = {}", key, expr)
+}
@@ -31,6 +31,14 @@ impl Spinner {
         }
     }
 
+    /// Hides the spinner while `f` runs, eg. so it can prompt.
+    pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        match &self.bar {
+            Some(bar) => bar.suspend(f),
+            None => f(),
+        }
+    }
+
     pub fn finish_and_clear(&self) {
         if let Some(bar) = &self.bar {
             bar.finish_and_clear();
@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use libpkgx::{
+    env::Source,
+    platform_case_aware_env_key::{construct_platform_case_aware_env_key, PlatformCaseAwareEnvKey},
+    types::Package,
+};
+
+use crate::diff::{diff, Change};
+
+fn env(vars: &[(&str, &str)]) -> HashMap<PlatformCaseAwareEnvKey, String> {
+    vars.iter()
+        .map(|(key, value)| {
+            (
+                construct_platform_case_aware_env_key(key.to_string()),
+                value.to_string(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn test_diff() {
+    let parent = env(&[
+        ("PATH", "/usr/bin"),
+        ("CFLAGS", "-O2"),
+        ("EMPTY", ""),
+        ("HOME", "/h"),
+    ]);
+    let new = env(&[
+        ("PATH", "/a/bin:/usr/bin"),
+        ("CFLAGS", "-O2 -I/a/include"),
+        ("EMPTY", ""),
+        ("HOME", "/h"),
+        ("PKGX_LVL", "1"),
+    ]);
+    let pkg = Package {
+        project: "a.com".to_string(),
+        version: libpkgx::Version::parse("1.0.0").unwrap(),
+    };
+    let sources = HashMap::from([(
+        construct_platform_case_aware_env_key("PATH".to_string()),
+        vec![Source::Path {
+            pkg,
+            suffix: "bin".to_string(),
+            path: "/a/bin".to_string(),
+        }],
+    )]);
+
+    let entries = diff(&parent, &new, &sources);
+    let changes: Vec<_> = entries
+        .iter()
+        .map(|entry| (entry.key.as_str(), &entry.change))
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            ("CFLAGS", &Change::Changed),
+            ("PATH", &Change::Prepended),
+            ("PKGX_LVL", &Change::Added)
+        ]
+    );
+    assert_eq!(entries[0].was.as_deref(), Some("-O2"));
+    assert!(matches!(entries[1].sources[..], [Source::Path { .. }]));
+    // nothing else sets it so it’s ours
+    assert!(matches!(entries[2].sources[..], [Source::Pkgx]));
+}
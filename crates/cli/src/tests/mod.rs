@@ -1 +1,7 @@
+mod diff;
 mod main;
+// paths are `;` separated on windows
+#[cfg(unix)]
+mod shell;
+mod tree;
+mod which;
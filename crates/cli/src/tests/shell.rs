@@ -0,0 +1,109 @@
+use libpkgx::env::EnvValue;
+
+use crate::shell::{restore, set, Shell};
+
+fn value(
+    default: Option<&str>,
+    prepend: Option<(&str, &str)>,
+    append: Option<(&str, &str)>,
+) -> EnvValue {
+    let pair = |(a, b): (&str, &str)| (a.to_string(), b.to_string());
+    EnvValue {
+        default: default.map(str::to_string),
+        prepend: prepend.map(pair),
+        append: append.map(pair),
+    }
+}
+
+#[test]
+fn test_set() {
+    let path = value(None, Some(("/a/bin:/b's/bin", ":")), None);
+    let msg = value(Some("it's \"$5\""), None, None);
+    let cflags = value(None, Some(("-I/a", " ")), None);
+    let both = value(Some("/d"), Some(("/a", ":")), Some(("; ", "/c")));
+
+    assert_eq!(
+        set(Some(Shell::Bash), "MSG", &msg),
+        "[ -n \"${MSG:-}\" ] || export MSG=\"it's \\\"\\$5\\\"\""
+    );
+    assert_eq!(
+        set(Some(Shell::Posix), "CFLAGS", &cflags),
+        "export CFLAGS=\"-I/a${CFLAGS:+ ${CFLAGS}}\""
+    );
+    assert_eq!(
+        set(Some(Shell::Posix), "FOO", &both),
+        "[ -n \"${FOO:-}\" ] || export FOO=\"/d\"\nexport FOO=\"/a${FOO:+:${FOO}}; /c\""
+    );
+
+    assert_eq!(
+        set(Some(Shell::Fish), "PATH", &path),
+        "set -gx --path PATH '/a/bin' '/b\\'s/bin' $PATH"
+    );
+    assert_eq!(
+        set(Some(Shell::Fish), "MSG", &msg),
+        "test -n \"$MSG\"; or set -gx MSG 'it\\'s \"$5\"'"
+    );
+    assert_eq!(
+        set(Some(Shell::Fish), "CFLAGS", &cflags),
+        "set -gx CFLAGS (string join ' ' '-I/a' $CFLAGS)"
+    );
+
+    assert_eq!(
+        set(Some(Shell::Nu), "PATH", &path),
+        "$env.PATH = ($env.PATH? | default [] | split row (char esep) | prepend [\"/a/bin\", \"/b's/bin\"])"
+    );
+    assert_eq!(
+        set(Some(Shell::Nu), "CFLAGS", &cflags),
+        "$env.CFLAGS = ([\"-I/a\", ($env.CFLAGS? | default \"\")] | where $it != \"\" | str join \" \")"
+    );
+
+    assert_eq!(
+        set(Some(Shell::Pwsh), "MSG", &msg),
+        "if (-not $env:MSG) { $env:MSG = 'it''s \"$5\"' }"
+    );
+    assert_eq!(
+        set(Some(Shell::Pwsh), "CFLAGS", &cflags),
+        "$env:CFLAGS = ((@('-I/a', $env:CFLAGS) | Where-Object { $_ }) -join ' ')"
+    );
+}
+
+// without `--shell` it must be what `pkgx +pkg` has always printed
+#[test]
+fn test_set_historic() {
+    let path = value(None, Some(("/a/bin:/b/bin", ":")), None);
+    let cflags = value(None, Some(("-I/a/include", " ")), None);
+    let msg = value(Some("hello world"), None, None);
+    let both = value(Some("/d"), Some(("/a", " ")), Some((":", "/c")));
+    let after = value(None, None, Some((":", "/c")));
+
+    assert_eq!(
+        set(None, "PATH", &path),
+        "PATH=\"/a/bin:/b/bin${PATH:+:$PATH}\""
+    );
+    assert_eq!(
+        set(None, "CFLAGS", &cflags),
+        "CFLAGS=\"-I/a/include $CFLAGS\""
+    );
+    assert_eq!(set(None, "MSG", &msg), "MSG=\"${MSG:-hello world}\"");
+    assert_eq!(set(None, "FOO", &both), "FOO=\"/a ${FOO:-/d}:/c\"");
+    assert_eq!(set(None, "BAR", &after), "BAR=\"$BAR:/c\"");
+}
+
+#[test]
+fn test_restore() {
+    assert_eq!(
+        restore(Some(Shell::Zsh), "FOO", Some("a \"b\"")),
+        "export FOO=\"a \\\"b\\\"\""
+    );
+    assert_eq!(restore(Some(Shell::Bash), "FOO", None), "unset FOO");
+    assert_eq!(
+        restore(Some(Shell::Fish), "PATH", Some("/a:/b")),
+        "set -gx --path PATH (string split ':' '/a:/b')"
+    );
+    assert_eq!(restore(Some(Shell::Fish), "FOO", None), "set -e FOO");
+    assert_eq!(restore(Some(Shell::Nu), "FOO", None), "hide-env -i FOO");
+    assert_eq!(
+        restore(Some(Shell::Pwsh), "FOO", None),
+        "Remove-Item Env:FOO -ErrorAction SilentlyContinue"
+    );
+}
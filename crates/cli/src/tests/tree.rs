@@ -0,0 +1,61 @@
+use crate::tree::{render, to_dot, Edge, Node};
+
+fn node(project: &str, deps: Vec<(&str, &str, usize)>) -> Node {
+    Node {
+        project: project.to_string(),
+        version: "1.0.0".to_string(),
+        installed: false,
+        requested: false,
+        companion: false,
+        deps: deps
+            .into_iter()
+            .map(|(req, pkg, index)| Edge {
+                req: req.to_string(),
+                pkg: pkg.to_string(),
+                index,
+            })
+            .collect(),
+    }
+}
+
+fn graph() -> Vec<Node> {
+    let mut nodes = vec![
+        node(
+            "a.com",
+            vec![("b.com^1", "b.com=1.0.0", 1), ("c.com^1", "c.com=1.0.0", 2)],
+        ),
+        node("b.com", vec![("c.com^1", "c.com=1.0.0", 2)]),
+        node("c.com", vec![("d.com^1", "d.com=1.0.0", 3)]),
+        node("d.com", vec![]),
+        node("e.com", vec![]),
+    ];
+    nodes[0].requested = true;
+    nodes[3].installed = true;
+    nodes[4].companion = true;
+    nodes
+}
+
+#[test]
+fn test_render_tree() {
+    assert_eq!(
+        render(&graph(), &[0, 4]),
+        "\
+a.com=1.0.0
+├─ b.com=1.0.0
+│  └─ c.com=1.0.0
+│     └─ d.com=1.0.0 (installed)
+└─ c.com=1.0.0 (*)
+e.com=1.0.0 (companion)
+"
+    );
+}
+
+#[test]
+fn test_to_dot() {
+    let dot = to_dot(&graph());
+    assert!(dot.starts_with("digraph pkgx {\n"));
+    assert!(dot.contains("  \"a.com=1.0.0\" [style=\"bold\"];\n"));
+    assert!(dot.contains("  \"e.com=1.0.0\" [style=\"dashed\"];\n"));
+    assert!(dot.contains("  \"a.com=1.0.0\" -> \"b.com=1.0.0\" [label=\"b.com^1\"];\n"));
+    assert!(dot.ends_with("}\n"));
+}
@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+
+use crate::which::{Preferences, WhichError};
+
+#[test]
+fn test_pick() {
+    let candidates = vec!["classic.yarnpkg.com".to_string(), "yarnpkg.com".to_string()];
+
+    assert_eq!(Preferences::default().pick("yarn", &candidates), None);
+
+    let saved = BTreeMap::from([("yarn".to_string(), "yarnpkg.com".to_string())]);
+    let prefs = Preferences {
+        saved: saved.clone(),
+        ..Default::default()
+    };
+    assert_eq!(
+        prefs.pick("yarn", &candidates),
+        Some("yarnpkg.com".to_string())
+    );
+    // only for the program it was chosen for
+    assert_eq!(prefs.pick("yarnpkg", &candidates), None);
+
+    // --prefer wins over a saved choice
+    let prefs = Preferences {
+        prefer: vec!["classic.yarnpkg.com".to_string()],
+        saved,
+        ..Default::default()
+    };
+    assert_eq!(
+        prefs.pick("yarn", &candidates),
+        Some("classic.yarnpkg.com".to_string())
+    );
+
+    // a stale saved choice is ignored
+    let prefs = Preferences {
+        saved: BTreeMap::from([("yarn".to_string(), "npmjs.com".to_string())]),
+        ..Default::default()
+    };
+    assert_eq!(prefs.pick("yarn", &candidates), None);
+}
+
+#[test]
+fn test_multiple_projects_message() {
+    let err = WhichError::MultipleProjects(
+        "yarn".to_string(),
+        vec!["classic.yarnpkg.com".to_string(), "yarnpkg.com".to_string()],
+    );
+    assert_eq!(
+        err.to_string(),
+        "multiple projects provide yarn, choose with `pkgx +classic.yarnpkg.com yarn` or `pkgx +yarnpkg.com yarn` (or --prefer)"
+    );
+}
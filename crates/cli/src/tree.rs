@@ -0,0 +1,221 @@
+use std::{collections::HashSet, error::Error};
+
+use libpkgx::{
+    config::Config,
+    hydrate::Policy,
+    pantry_db,
+    types::{Package, PackageReq},
+};
+use rusqlite::Connection;
+use serde::Serialize;
+
+use crate::{
+    args::Flags,
+    resolve::{self, parse_pkgspec, resolve_project},
+    which::Preferences,
+};
+
+#[derive(Serialize)]
+pub(crate) struct Node {
+    pub project: String,
+    pub version: String,
+    pub installed: bool,
+    /// asked for rather than pulled in
+    pub requested: bool,
+    /// brought in as a companion of something requested
+    pub companion: bool,
+    pub deps: Vec<Edge>,
+}
+
+#[derive(Serialize)]
+pub(crate) struct Edge {
+    /// the requirement as the pantry states it
+    pub req: String,
+    /// what satisfies it
+    pub pkg: String,
+    #[serde(skip)]
+    pub index: usize,
+}
+
+/// Prints what `+pkg…` resolves to without installing anything.
+pub async fn tree(
+    args: &[String],
+    plus: &[String],
+    dot: bool,
+    flags: &Flags,
+    conn: &Connection,
+    config: &Config,
+) -> Result<(), Box<dyn Error>> {
+    let prefs = &Preferences::load(flags);
+    let mut reqs = vec![];
+    for pkgspec in plus.iter().chain(args) {
+        let mut pkgspec = parse_pkgspec(pkgspec)?;
+        resolve_project(&mut pkgspec, config, conn, &reqs, prefs)?;
+        reqs.push(pkgspec.pkgreq(config).await);
+    }
+
+    let projects: Vec<String> = reqs.iter().map(|req| req.project.clone()).collect();
+    let companions = pantry_db::companions_for_projects(&projects, conn)?;
+
+    for req in reqs.iter().chain(&companions) {
+        pantry_db::ensure_supported(&req.project, &config.platform, conn)?;
+    }
+
+    let deps = |pkg: &Package| resolve::deps(pkg, config, conn);
+    let resolution = libpkgx::resolve::solve(
+        &[reqs.clone(), companions.clone()].concat(),
+        &Policy::default(),
+        config,
+        deps,
+    )
+    .await?;
+    let pkgs = &resolution.pkgs;
+
+    let find = |req: &PackageReq| {
+        pkgs.iter()
+            .position(|pkg| pkg.project == req.project && req.constraint.satisfies(&pkg.version))
+    };
+
+    let mut nodes = vec![];
+    for pkg in pkgs {
+        let mut edges = vec![];
+        for dep in deps(pkg)? {
+            if let Some(index) = find(&dep) {
+                edges.push(Edge {
+                    req: dep.to_string(),
+                    pkg: pkgs[index].to_string(),
+                    index,
+                });
+            }
+        }
+        nodes.push(Node {
+            project: pkg.project.clone(),
+            version: pkg.version.to_string(),
+            installed: resolution
+                .installed
+                .iter()
+                .any(|installation| installation.pkg.to_string() == pkg.to_string()),
+            requested: false,
+            companion: false,
+            deps: edges,
+        });
+    }
+
+    let mut roots = vec![];
+    for (req, companion) in reqs
+        .iter()
+        .map(|req| (req, false))
+        .chain(companions.iter().map(|req| (req, true)))
+    {
+        if let Some(index) = find(req) {
+            if companion {
+                nodes[index].companion = !nodes[index].requested;
+            } else {
+                nodes[index].requested = true;
+            }
+            if !roots.contains(&index) {
+                roots.push(index);
+            }
+        }
+    }
+
+    if flags.json.is_some() {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&serde_json::json!({ "pkgs": nodes }))?
+        );
+    } else if dot {
+        print!("{}", to_dot(&nodes));
+    } else {
+        print!("{}", render(&nodes, &roots));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn render(nodes: &[Node], roots: &[usize]) -> String {
+    let mut out = String::new();
+    let mut seen = HashSet::new();
+    for &index in roots {
+        render_node(&mut out, nodes, index, "", "", &mut seen);
+    }
+    out
+}
+
+fn label(node: &Node) -> String {
+    let mut label = format!("{}={}", node.project, node.version);
+    if node.companion {
+        label.push_str(" (companion)");
+    }
+    if node.installed {
+        label.push_str(" (installed)");
+    }
+    label
+}
+
+// subtrees are printed once, repeats are marked with `(*)` like `cargo tree`
+fn render_node(
+    out: &mut String,
+    nodes: &[Node],
+    index: usize,
+    prefix: &str,
+    branch: &str,
+    seen: &mut HashSet<usize>,
+) {
+    let node = &nodes[index];
+    let first_visit = seen.insert(index);
+    let repeat = if first_visit || node.deps.is_empty() {
+        ""
+    } else {
+        " (*)"
+    };
+    out.push_str(&format!("{}{}{}{}\n", prefix, branch, label(node), repeat));
+
+    if first_visit {
+        let prefix = match branch {
+            "├─ " => format!("{}│  ", prefix),
+            "└─ " => format!("{}   ", prefix),
+            _ => prefix.to_string(),
+        };
+        for (n, edge) in node.deps.iter().enumerate() {
+            let branch = if n == node.deps.len() - 1 {
+                "└─ "
+            } else {
+                "├─ "
+            };
+            render_node(out, nodes, edge.index, &prefix, branch, seen);
+        }
+    }
+}
+
+pub(crate) fn to_dot(nodes: &[Node]) -> String {
+    let mut dot = String::from("digraph pkgx {\n");
+    for node in nodes {
+        let mut style = vec![];
+        if node.requested {
+            style.push("bold");
+        }
+        if node.companion {
+            style.push("dashed");
+        }
+        if node.installed {
+            style.push("filled");
+        }
+        dot.push_str(&format!(
+            "  \"{}={}\" [style=\"{}\"];\n",
+            node.project,
+            node.version,
+            style.join(",")
+        ));
+    }
+    for node in nodes {
+        for edge in &node.deps {
+            dot.push_str(&format!(
+                "  \"{}={}\" -> \"{}\" [label=\"{}\"];\n",
+                node.project, node.version, edge.pkg, edge.req
+            ));
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
@@ -1,19 +1,44 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufRead, IsTerminal, Write},
+    path::PathBuf,
+};
+
 use libpkgx::types::PackageReq;
 use rusqlite::Connection;
 
+use crate::args::Flags;
+
 #[derive(Debug)]
 pub enum WhichError {
-    CmdNotFound(String),
+    /// and any programs with similar names
+    CmdNotFound(String, Vec<String>),
     MultipleProjects(String, Vec<String>),
-    DbError(rusqlite::Error),
+    DbError(libpkgx::Error),
 }
 
 impl std::fmt::Display for WhichError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            WhichError::CmdNotFound(cmd) => write!(f, "cmd not found: {}", cmd),
+            WhichError::CmdNotFound(cmd, suggestions) if !suggestions.is_empty() => write!(
+                f,
+                "cmd not found: {}, did you mean: {}?",
+                cmd,
+                suggestions.join(", ")
+            ),
+            WhichError::CmdNotFound(cmd, _) => write!(f, "cmd not found: {}", cmd),
             WhichError::MultipleProjects(cmd, projects) => {
-                write!(f, "multiple projects found for {}: {:?}", cmd, projects)
+                let incantations: Vec<String> = projects
+                    .iter()
+                    .map(|project| format!("`pkgx +{} {}`", project, cmd))
+                    .collect();
+                write!(
+                    f,
+                    "multiple projects provide {}, choose with {} (or --prefer)",
+                    cmd,
+                    incantations.join(" or ")
+                )
             }
             WhichError::DbError(err) => write!(f, "db error: {}", err),
         }
@@ -22,17 +47,103 @@ impl std::fmt::Display for WhichError {
 
 impl std::error::Error for WhichError {}
 
-pub async fn which(
+/// How to choose when more than one project provides a program.
+#[derive(Default)]
+pub struct Preferences {
+    /// from `--prefer`
+    pub prefer: Vec<String>,
+    /// program → project, as chosen before
+    pub saved: BTreeMap<String, String>,
+    pub path: Option<PathBuf>,
+    /// ask on the terminal if it is still ambiguous
+    pub interactive: bool,
+}
+
+impl Preferences {
+    pub fn load(flags: &Flags) -> Self {
+        let path = dirs_next::config_dir().map(|dir| dir.join("pkgx/preferences.json"));
+        let saved = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Self {
+            prefer: flags.prefer.clone(),
+            saved,
+            path,
+            interactive: !flags.silent
+                && flags.json.is_none()
+                && io::stdin().is_terminal()
+                && io::stderr().is_terminal(),
+        }
+    }
+
+    /// `--prefer` wins over what was chosen before.
+    pub fn pick(&self, cmd: &str, candidates: &[String]) -> Option<String> {
+        let preferred: Vec<&String> = candidates
+            .iter()
+            .filter(|candidate| self.prefer.contains(candidate))
+            .collect();
+        if preferred.len() == 1 {
+            return Some(preferred[0].clone());
+        }
+        self.saved
+            .get(cmd)
+            .filter(|project| candidates.contains(project))
+            .cloned()
+    }
+
+    fn ask(&self, cmd: &str, candidates: &[String]) -> Option<String> {
+        if !self.interactive {
+            return None;
+        }
+        eprintln!("multiple projects provide {}:", cmd);
+        for (n, candidate) in candidates.iter().enumerate() {
+            eprintln!("  {}. {}", n + 1, candidate);
+        }
+        eprint!("choose [1-{}]: ", candidates.len());
+        io::stderr().flush().ok()?;
+
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line).ok()?;
+        let n: usize = line.trim().parse().ok()?;
+        let project = candidates.get(n.checked_sub(1)?)?.clone();
+
+        if let Some(path) = &self.path {
+            if self.remember(path, cmd, &project).is_ok() {
+                eprintln!("remembered in {}", path.display());
+            }
+        }
+        Some(project)
+    }
+
+    // re-read so we don’t clobber a choice another pkgx made meanwhile
+    fn remember(&self, path: &PathBuf, cmd: &str, project: &str) -> io::Result<()> {
+        let mut saved: BTreeMap<String, String> = fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        saved.insert(cmd.to_string(), project.to_string());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&saved)? + "\n")
+    }
+}
+
+pub fn which(
     cmd: &String,
     conn: &Connection,
     pkgs: &[PackageReq],
+    prefs: &Preferences,
 ) -> Result<String, WhichError> {
     let candidates =
         libpkgx::pantry_db::projects_for_symbol(cmd, conn).map_err(WhichError::DbError)?;
     if candidates.len() == 1 {
         Ok(candidates[0].clone())
     } else if candidates.is_empty() {
-        Err(WhichError::CmdNotFound(cmd.clone()))
+        let suggestions = libpkgx::pantry_db::suggestions(cmd, conn).unwrap_or_default();
+        Err(WhichError::CmdNotFound(cmd.clone(), suggestions))
     } else {
         let selected_pkgs = candidates
             .clone()
@@ -46,6 +157,10 @@ pub async fn which(
             .collect::<Vec<String>>();
         if selected_pkgs.len() == 1 {
             Ok(selected_pkgs[0].clone())
+        } else if let Some(project) = prefs.pick(cmd, &candidates) {
+            Ok(project)
+        } else if let Some(project) = prefs.ask(cmd, &candidates) {
+            Ok(project)
         } else {
             Err(WhichError::MultipleProjects(cmd.clone(), candidates))
         }
@@ -1,14 +1,15 @@
 use std::{collections::HashMap, result::Result};
 
 use libpkgx::{
+    config::Config,
     platform_case_aware_env_key::{construct_platform_case_aware_env_key, PlatformCaseAwareEnvKey},
     types::{Installation, PackageReq},
     utils,
 };
-use regex::Regex;
 
 use crate::args::Flags;
 
+#[allow(clippy::too_many_arguments)]
 pub async fn exec(
     find_program: bool,
     mut args: Vec<String>,
@@ -16,6 +17,7 @@ pub async fn exec(
     env: HashMap<String, Vec<String>>,
     flags: Flags,
     conn: rusqlite::Connection,
+    config: &Config,
     graph: Vec<PackageReq>,
 ) -> Result<
     (
@@ -61,28 +63,8 @@ pub async fn exec(
         }
         utils::find_program(&args.remove(0), &paths).await?
     };
-    let env = libpkgx::env::mix(env);
-    let mut env = libpkgx::env::mix_runtime(&env, &installations, &conn)?;
-
-    let re = Regex::new(r"^\$\{\w+:-([^}]+)\}$").unwrap();
-
-    #[cfg(unix)]
-    let sep = ":";
-    #[cfg(windows)]
-    let sep = ";";
-
-    for (key, value) in env.clone() {
-        if let Some(caps) = re.captures(&value) {
-            env.insert(key, caps.get(1).unwrap().as_str().to_string());
-        } else {
-            let cleaned_value = value
-                .replace(&format!("{}${}", sep, key), "")
-                .replace(&format!("${}{}", key, sep), "")
-                .replace(&format!("; ${}", key), "") // one pantry instance of this
-                .replace(&format!("${}", key), "");
-            env.insert(key, cleaned_value);
-        }
-    }
+    let values = libpkgx::env::runtime(&installations, &conn, config)?;
+    let mut env = libpkgx::env::mix(&values);
 
     // fork bomb protection
     env.insert(
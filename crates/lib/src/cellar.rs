@@ -1,17 +1,19 @@
 use crate::config::Config;
+use crate::error::Error;
 use crate::types::{Installation, Package, PackageReq};
+use fs2::FileExt;
 use libsemverator::semver::Semver as Version;
-use std::error::Error;
+use std::fs::OpenOptions;
 use std::path::PathBuf;
-use tokio::fs;
+use tokio::{fs, task};
 
-pub async fn ls(project: &str, config: &Config) -> Result<Vec<Installation>, Box<dyn Error>> {
+pub async fn ls(project: &str, config: &Config) -> Result<Vec<Installation>, Error> {
     let d = config.pkgx_dir.join(project);
 
     match fs::metadata(&d).await {
         Ok(metadata) => {
             if !metadata.is_dir() {
-                return Err(format!("err: expected directory: {:?}", d).into());
+                return Err(Error::Other(format!("err: expected directory: {:?}", d)));
             }
         }
         Err(e) => {
@@ -50,10 +52,49 @@ pub async fn ls(project: &str, config: &Config) -> Result<Vec<Installation>, Box
     Ok(rv)
 }
 
-pub async fn resolve(
-    pkgreq: &PackageReq,
-    config: &Config,
-) -> Result<Option<Installation>, Box<dyn Error>> {
+/// Every installation of every project in the cellar.
+pub async fn ls_all(config: &Config) -> Result<Vec<Installation>, Error> {
+    let mut rv = vec![];
+    // projects may be nested, eg. github.com/foo/bar
+    let mut stack = vec![config.pkgx_dir.clone()];
+    while let Some(dir) = stack.pop() {
+        let mut entries = match fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let top = dir == config.pkgx_dir;
+        let mut is_shelf = false;
+        let mut subdirs = vec![];
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') || !entry.file_type().await?.is_dir() {
+                continue;
+            }
+            if name.starts_with('v') && Version::parse(&name[1..]).is_ok() {
+                is_shelf = true;
+            } else if !top || name.contains('.') {
+                // every project starts with a domain, eg. example.com
+                subdirs.push(entry.path());
+            }
+        }
+        if is_shelf {
+            // anything else in a shelf is the project’s, eg. its `var`
+            let project = dir
+                .strip_prefix(&config.pkgx_dir)
+                .map_err(|err| Error::Other(err.to_string()))?
+                .to_string_lossy();
+            #[cfg(windows)]
+            let project = project.replace("\\", "/");
+            rv.extend(ls(&project, config).await?);
+        } else {
+            stack.extend(subdirs);
+        }
+    }
+    Ok(rv)
+}
+
+pub async fn resolve(pkgreq: &PackageReq, config: &Config) -> Result<Option<Installation>, Error> {
     Ok(ls(&pkgreq.project, config)
         .await?
         .iter()
@@ -68,3 +109,30 @@ pub fn dst(pkg: &Package, config: &Config) -> PathBuf {
         .join(pkg.project.clone())
         .join(format!("v{}", pkg.version.raw))
 }
+
+/// Exclusively locks a project’s shelf, waiting for other pkgx instances to release it.
+pub(crate) async fn lock(project: &str, config: &Config) -> Result<std::fs::File, Error> {
+    let shelf = config.pkgx_dir.join(project);
+    std::fs::create_dir_all(&shelf)?;
+
+    #[cfg(windows)]
+    let lockfile = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(shelf.join("lockfile"))?;
+    #[cfg(not(windows))]
+    let lockfile = OpenOptions::new()
+        .read(true) // Open the directory in read-only mode
+        .open(shelf.clone())?;
+
+    task::spawn_blocking({
+        let lockfile = lockfile.try_clone()?;
+        move || lockfile.lock_exclusive()
+    })
+    .await?
+    .map_err(|_| Error::Locked(shelf))?;
+
+    Ok(lockfile)
+}
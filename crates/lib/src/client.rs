@@ -2,11 +2,13 @@ use std::env;
 
 use reqwest::{Client, ClientBuilder};
 
+use crate::error::Error;
+
 #[cfg(not(any(target_os = "macos", target_os = "windows")))]
 const CERT: &[u8] = include_bytes!("amazon_root_ca1.pem");
 
 #[cfg(not(any(target_os = "macos", target_os = "windows")))]
-pub fn build_client() -> Result<Client, Box<dyn std::error::Error>> {
+pub fn build_client() -> Result<Client, Error> {
     let mut builder = ClientBuilder::new();
 
     let bndl = reqwest::Certificate::from_pem_bundle(CERT)?;
@@ -20,7 +22,7 @@ pub fn build_client() -> Result<Client, Box<dyn std::error::Error>> {
 }
 
 #[cfg(any(target_os = "macos", target_os = "windows"))]
-pub fn build_client() -> Result<Client, Box<dyn std::error::Error>> {
+pub fn build_client() -> Result<Client, Error> {
     Ok(ClientBuilder::new().user_agent(get_user_agent()).build()?)
 }
 
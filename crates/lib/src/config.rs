@@ -1,53 +1,185 @@
+use std::collections::HashMap;
 use std::env;
+use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::pantry::OneOrMany;
+use crate::pantry_db;
+use crate::types::Platform;
 
 #[derive(Debug)]
 pub struct Config {
     pub pantry_dir: PathBuf,
+    /// more pantries layered over `pantry_dir`, a project in an earlier one
+    /// shadows the same project in later ones and in `pantry_dir`
+    pub pantry_overlays: Vec<PathBuf>,
     pub pantry_db_file: PathBuf,
-    pub dist_url: String,
+    /// mirrors of the dist server, each request falls through to the next
+    /// if one can’t be reached or doesn’t have what we want
+    pub dist_urls: Vec<String>,
     pub pkgx_dir: PathBuf,
+    /// never touch the network, only the cellar and cached inventories are used
+    pub offline: bool,
+    /// how long a cached `versions.txt` is used before we revalidate it
+    pub inventory_ttl: Duration,
+    /// how many bottles we download at once
+    pub download_concurrency: usize,
+    /// what we resolve and fetch for, see `cross`
+    pub platform: Platform,
+    /// the user’s `paths.yml`, variables whose directories replace ours, see
+    /// `env::Paths`
+    pub env_paths: HashMap<String, Vec<String>>,
+    /// what is wrong with the user’s configuration that we ignored instead,
+    /// for the caller to show
+    pub warnings: Vec<String>,
 }
 
 impl Config {
     pub fn new() -> io::Result<Self> {
         let pantry_dir = get_pantry_dir()?;
+        let pantry_overlays = get_pantry_overlays();
         let pantry_db_file: PathBuf = get_pantry_db_file()?;
-        let dist_url = get_dist_url();
+        let dist_urls = get_dist_urls();
         let pkgx_dir = get_pkgx_dir()?;
+        let offline = env::var("PKGX_OFFLINE").is_ok();
+        let inventory_ttl = get_inventory_ttl();
+        let download_concurrency = get_download_concurrency();
+        let mut warnings = vec![];
+        let env_paths = get_env_paths().unwrap_or_else(|err| {
+            warnings.push(format!("{} (ignored)", err));
+            HashMap::new()
+        });
         Ok(Self {
             pantry_dir,
+            pantry_overlays,
             pantry_db_file,
-            dist_url,
+            dist_urls,
             pkgx_dir,
+            offline,
+            inventory_ttl,
+            download_concurrency,
+            platform: Platform::current(),
+            env_paths,
+            warnings,
         })
     }
+
+    /// Resolve and fetch for another platform. Its bottles can’t run here so
+    /// they must go somewhere of their own (`PKGX_DIR`) and as the pantry says
+    /// different things for different platforms it gets its own pantry db.
+    pub fn cross(&mut self, platform: Platform) -> io::Result<()> {
+        if platform == Platform::current() {
+            return Ok(());
+        }
+        if !env::var("PKGX_DIR").is_ok_and(|dir| Path::new(&dir).is_absolute()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "set PKGX_DIR to an absolute path for the {} packages",
+                    platform
+                ),
+            ));
+        }
+        let db = format!(
+            "{}.{}-{}.db",
+            pantry_db::FILENAME.trim_end_matches(".db"),
+            platform.host,
+            platform.arch
+        );
+        self.pantry_db_file.set_file_name(db);
+        self.platform = platform;
+        Ok(())
+    }
+
+    /// Every pantry in order of precedence, `pantry_dir` last.
+    pub fn pantries(&self) -> Vec<&Path> {
+        self.pantry_overlays
+            .iter()
+            .map(PathBuf::as_path)
+            .chain([self.pantry_dir.as_path()])
+            .collect()
+    }
+}
+
+// `PKGX_DIST_URL` may list several mirrors separated by commas or whitespace
+fn get_dist_urls() -> Vec<String> {
+    let urls = env::var("PKGX_DIST_URL").unwrap_or_else(|_| env!("PKGX_DIST_URL").to_string());
+    let urls: Vec<String> = urls
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|url| !url.is_empty())
+        .map(|url| url.trim_end_matches('/').to_string())
+        .collect();
+    if urls.is_empty() {
+        vec![env!("PKGX_DIST_URL").to_string()]
+    } else {
+        urls
+    }
+}
+
+fn get_inventory_ttl() -> Duration {
+    env::var("PKGX_INVENTORY_TTL")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(60 * 60))
+}
+
+fn get_download_concurrency() -> usize {
+    env::var("PKGX_DOWNLOAD_CONCURRENCY")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(8)
+        .max(1)
 }
 
-fn get_dist_url() -> String {
-    if let Ok(env_url) = env::var("PKGX_DIST_URL") {
-        return env_url;
+// eg. `PYTHONPATH: lib/python3.12/site-packages`
+fn get_env_paths() -> Result<HashMap<String, Vec<String>>, String> {
+    let Some(path) = dirs_next::config_dir().map(|dir| dir.join("pkgx/paths.yml")) else {
+        return Ok(HashMap::new());
+    };
+    let Ok(yaml) = fs::read_to_string(&path) else {
+        return Ok(HashMap::new());
+    };
+    let paths: HashMap<String, OneOrMany> =
+        serde_yaml::from_str(&yaml).map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok(paths
+        .into_iter()
+        .map(|(key, suffixes)| (key, suffixes.0))
+        .collect())
+}
+
+fn absolute(path: PathBuf) -> Option<PathBuf> {
+    if path.is_absolute() {
+        Some(path)
+    } else if let Ok(cwd) = env::current_dir() {
+        Some(cwd.join(path))
+    } else {
+        None
     }
-    env!("PKGX_DIST_URL").to_string()
 }
 
 #[allow(non_snake_case)]
 fn get_PKGX_PANTRY_DIR() -> Option<PathBuf> {
     if let Ok(env_dir) = env::var("PKGX_PANTRY_DIR") {
-        let path = PathBuf::from(env_dir);
-        if path.is_absolute() {
-            Some(path)
-        } else if let Ok(cwd) = env::current_dir() {
-            Some(cwd.join(path))
-        } else {
-            None
-        }
+        absolute(PathBuf::from(env_dir))
     } else {
         None
     }
 }
 
+// `PKGX_PANTRY_PATH` is separated like `PATH`, first wins
+fn get_pantry_overlays() -> Vec<PathBuf> {
+    match env::var_os("PKGX_PANTRY_PATH") {
+        Some(paths) => env::split_paths(&paths)
+            .filter(|path| !path.as_os_str().is_empty())
+            .filter_map(absolute)
+            .collect(),
+        None => vec![],
+    }
+}
+
 fn get_pantry_dir() -> io::Result<PathBuf> {
     if let Some(path) = get_PKGX_PANTRY_DIR() {
         Ok(path)
@@ -90,9 +222,9 @@ fn get_pkgx_dir() -> io::Result<PathBuf> {
 
 fn get_pantry_db_file() -> io::Result<PathBuf> {
     if let Some(path) = get_PKGX_PANTRY_DIR() {
-        Ok(path.join("pantry.2.db"))
+        Ok(path.join(pantry_db::FILENAME))
     } else if let Some(path) = dirs_next::cache_dir() {
-        Ok(path.join("pkgx/pantry.2.db"))
+        Ok(path.join("pkgx").join(pantry_db::FILENAME))
     } else {
         Err(io::Error::new(
             io::ErrorKind::NotFound,
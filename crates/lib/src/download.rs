@@ -0,0 +1,194 @@
+use std::{io, time::Duration};
+
+use bytes::Bytes;
+use futures::stream::{self, BoxStream, StreamExt};
+use reqwest::{header::RANGE, Client, RequestBuilder, Response, StatusCode};
+
+use crate::error::Error;
+
+const MAX_RETRIES: u32 = 4;
+const BACKOFF: Duration = Duration::from_millis(250);
+
+/// GETs `url`, retrying transient failures with exponential backoff.
+pub(crate) async fn get(client: &Client, url: &str) -> Result<Response, reqwest::Error> {
+    send(&|url| client.get(url), url, false).await
+}
+
+/// Sends `request` for `path` to each of `mirrors` in turn until one serves
+/// it, returning the response and the mirror that served it.
+///
+/// A mirror that can’t be reached, doesn’t have `path` or is failing is
+/// skipped for the next, the last mirror’s error is returned if all fail.
+pub(crate) async fn get_mirrored<F>(
+    mirrors: &[String],
+    path: &str,
+    request: F,
+) -> Result<(Response, String), Error>
+where
+    F: Fn(&str) -> RequestBuilder,
+{
+    for (n, mirror) in mirrors.iter().enumerate() {
+        let failover = n + 1 < mirrors.len();
+        match send(&request, &format!("{}/{}", mirror, path), failover).await {
+            Ok(rsp) => return Ok((rsp, mirror.clone())),
+            Err(err) if failover && falls_through(&err) => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Err(Error::Other("no dist mirrors configured".to_string()))
+}
+
+async fn send<F>(request: &F, url: &str, failover: bool) -> Result<Response, reqwest::Error>
+where
+    F: Fn(&str) -> RequestBuilder,
+{
+    let mut attempt = 0;
+    loop {
+        match request(url)
+            .send()
+            .await
+            .and_then(|rsp| rsp.error_for_status())
+        {
+            // no point waiting on a mirror that is down when there is another
+            Err(err) if failover && err.is_connect() => return Err(err),
+            Err(err) if attempt < MAX_RETRIES && is_transient(&err) => {
+                backoff(attempt).await;
+                attempt += 1;
+            }
+            rv => return rv,
+        }
+    }
+}
+
+struct Resumable {
+    client: Client,
+    url: String,
+    stream: Option<BoxStream<'static, reqwest::Result<Bytes>>>,
+    /// bytes we have yielded so far
+    offset: u64,
+    total: u64,
+    /// bytes to discard if the server ignored our `Range` and started again
+    skip: u64,
+    attempt: u32,
+    done: bool,
+}
+
+/// The body of `rsp` as a stream that survives dropped connections by
+/// re-requesting the rest of `url` with an HTTP `Range`.
+pub(crate) fn resumable(
+    client: Client,
+    url: String,
+    rsp: Response,
+    total: u64,
+) -> BoxStream<'static, io::Result<Bytes>> {
+    let state = Resumable {
+        client,
+        url,
+        stream: Some(rsp.bytes_stream().boxed()),
+        offset: 0,
+        total,
+        skip: 0,
+        attempt: 0,
+        done: false,
+    };
+
+    stream::unfold(state, |mut state| async move {
+        if state.done {
+            return None;
+        }
+        loop {
+            let Some(stream) = state.stream.as_mut() else {
+                if let Err(err) = state.reconnect().await {
+                    state.done = true;
+                    return Some((Err(io::Error::other(err)), state));
+                }
+                continue;
+            };
+
+            match stream.next().await {
+                Some(Ok(mut chunk)) => {
+                    if state.skip > 0 {
+                        let n = state.skip.min(chunk.len() as u64);
+                        chunk = chunk.slice(n as usize..);
+                        state.skip -= n;
+                        if chunk.is_empty() {
+                            continue;
+                        }
+                    }
+                    state.offset += chunk.len() as u64;
+                    state.attempt = 0;
+                    return Some((Ok(chunk), state));
+                }
+                Some(Err(err)) if state.attempt < MAX_RETRIES && is_transient(&err) => {
+                    state.stream = None;
+                }
+                Some(Err(err)) => {
+                    state.done = true;
+                    return Some((Err(io::Error::other(err)), state));
+                }
+                // the connection closed cleanly but early
+                None if state.offset < state.total && state.attempt < MAX_RETRIES => {
+                    state.stream = None;
+                }
+                None if state.offset < state.total => {
+                    state.done = true;
+                    let err = io::Error::new(io::ErrorKind::UnexpectedEof, "download truncated");
+                    return Some((Err(err), state));
+                }
+                None => return None,
+            }
+        }
+    })
+    .boxed()
+}
+
+impl Resumable {
+    async fn reconnect(&mut self) -> Result<(), reqwest::Error> {
+        loop {
+            backoff(self.attempt).await;
+            self.attempt += 1;
+
+            let rsp = self
+                .client
+                .get(&self.url)
+                .header(RANGE, format!("bytes={}-", self.offset))
+                .send()
+                .await
+                .and_then(|rsp| rsp.error_for_status());
+
+            match rsp {
+                Ok(rsp) => {
+                    self.skip = if rsp.status() == StatusCode::PARTIAL_CONTENT {
+                        0
+                    } else {
+                        self.offset
+                    };
+                    self.stream = Some(rsp.bytes_stream().boxed());
+                    return Ok(());
+                }
+                Err(err) if self.attempt < MAX_RETRIES && is_transient(&err) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+fn falls_through(err: &reqwest::Error) -> bool {
+    match err.status() {
+        Some(status) => status == StatusCode::NOT_FOUND || status.is_server_error(),
+        None => err.is_connect() || err.is_timeout(),
+    }
+}
+
+fn is_transient(err: &reqwest::Error) -> bool {
+    if let Some(status) = err.status() {
+        status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+    } else {
+        // a connection dropped mid-body surfaces as a decode error
+        err.is_connect() || err.is_timeout() || err.is_request() || err.is_body() || err.is_decode()
+    }
+}
+
+async fn backoff(attempt: u32) {
+    tokio::time::sleep(BACKOFF * 2u32.pow(attempt)).await;
+}
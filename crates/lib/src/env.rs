@@ -1,6 +1,5 @@
 use std::{
     collections::{HashMap, HashSet},
-    error::Error,
     path::PathBuf,
 };
 
@@ -8,56 +7,135 @@ use std::{
 use std::str::FromStr;
 
 use crate::{
+    config::Config,
+    error::Error,
+    moustache,
     platform_case_aware_env_key::{construct_platform_case_aware_env_key, PlatformCaseAwareEnvKey},
-    types::Installation,
+    types::{Installation, Package},
 };
 
+use serde::Serialize;
+
 #[cfg(unix)]
 pub const SEP: &str = ":";
 #[cfg(windows)]
 pub const SEP: &str = ";";
 
-pub fn map(installations: &Vec<Installation>) -> HashMap<String, Vec<String>> {
-    let mut vars: HashMap<EnvKey, OrderedSet<PathBuf>> = HashMap::new();
+/// Which directories of an installation go in which path-like variables,
+/// eg. `bin` in `PATH`.
+///
+/// Ours are the defaults, the pantry adds to them (`runtime.paths:`) and the
+/// user’s `paths.yml` (see `Config::env_paths`) replaces whole variables, eg.
+/// `LD_LIBRARY_PATH: []` to stop setting it.
+#[derive(Debug, Clone)]
+pub struct Paths(Vec<(String, Vec<String>)>);
 
-    let projects: HashSet<&str> = installations
-        .iter()
-        .map(|i| i.pkg.project.as_str())
-        .collect();
+const DEFAULT_PATHS: &[(&str, &[&str])] = &[
+    ("PATH", &["bin", "sbin"]),
+    ("MANPATH", &["man", "share/man"]),
+    ("PKG_CONFIG_PATH", &["share/pkgconfig", "lib/pkgconfig"]),
+    #[cfg(unix)]
+    ("LIBRARY_PATH", &["lib", "lib64"]),
+    #[cfg(unix)]
+    ("LD_LIBRARY_PATH", &["lib", "lib64"]),
+    #[cfg(unix)]
+    ("CPATH", &["include"]),
+    ("XDG_DATA_DIRS", &["share"]),
+    #[cfg(target_os = "macos")]
+    ("DYLD_FALLBACK_LIBRARY_PATH", &["lib", "lib64"]),
+    ("ACLOCAL_PATH", &["share/aclocal"]),
+    #[cfg(windows)]
+    ("LIB", &["lib"]),
+    #[cfg(windows)]
+    ("INCLUDE", &["include"]),
+];
 
-    for installation in installations {
-        for key in EnvKey::iter() {
-            if let Some(suffixes) = suffixes(&key) {
-                for suffix in suffixes {
-                    let path = installation.path.join(suffix);
-                    if path.is_dir() {
-                        vars.entry(key.clone())
-                            .or_insert_with(OrderedSet::new)
-                            .add(path);
-                    }
-                }
+// what projects need that their `package.yml`s don’t say (yet)
+const PROJECT_PATHS: &[(&str, &str, &[&str])] = &[("cmake.org", "CMAKE_PREFIX_PATH", &["."])];
+
+impl Default for Paths {
+    fn default() -> Self {
+        let mut rv = Self(vec![]);
+        for (key, suffixes) in DEFAULT_PATHS {
+            rv.add(key, suffixes.iter().map(|suffix| suffix.to_string()));
+        }
+        rv
+    }
+}
+
+impl Paths {
+    /// Ours, what the pantry says for `installations` and what the user says.
+    pub fn load(
+        installations: &Vec<Installation>,
+        conn: &Connection,
+        config: &Config,
+    ) -> Result<Self, Error> {
+        let mut rv = Self::default();
+        for installation in installations {
+            let project = &installation.pkg.project;
+            for (_, key, suffixes) in PROJECT_PATHS.iter().filter(|(p, ..)| p == project) {
+                rv.add(key, suffixes.iter().map(|suffix| suffix.to_string()));
+            }
+            for (key, suffix) in crate::pantry_db::runtime_paths_for_project(project, conn)? {
+                let suffix = moustache::render(&suffix, installation, installations, config)?;
+                rv.add(&key, [suffix]);
+            }
+        }
+        for (key, suffixes) in &config.env_paths {
+            match rv.0.iter_mut().find(|(k, _)| k == key) {
+                Some((_, ours)) => ours.clone_from(suffixes),
+                None => rv.0.push((key.clone(), suffixes.clone())),
             }
         }
+        Ok(rv)
+    }
 
-        if projects.contains("cmake.org") {
-            vars.entry(EnvKey::CmakePrefixPath)
-                .or_insert_with(OrderedSet::new)
-                .add(installation.path.clone());
+    fn add(&mut self, key: &str, suffixes: impl IntoIterator<Item = String>) {
+        match self.0.iter_mut().find(|(k, _)| k == key) {
+            Some((_, ours)) => ours.extend(suffixes),
+            None => self
+                .0
+                .push((key.to_string(), suffixes.into_iter().collect())),
         }
     }
+}
+
+// every directory `paths` puts in a variable, in order
+fn dirs<'a>(
+    installations: &'a [Installation],
+    paths: &'a Paths,
+) -> impl Iterator<Item = (&'a str, &'a Installation, &'a str, PathBuf)> {
+    installations.iter().flat_map(move |installation| {
+        paths.0.iter().flat_map(move |(key, suffixes)| {
+            suffixes.iter().filter_map(move |suffix| {
+                // `.` is the installation itself, eg. for `CMAKE_PREFIX_PATH`
+                let path = match suffix.as_str() {
+                    "." | "" => installation.path.clone(),
+                    suffix => installation.path.join(suffix),
+                };
+                path.is_dir()
+                    .then_some((key.as_str(), installation, suffix.as_str(), path))
+            })
+        })
+    })
+}
+
+pub fn map(installations: &[Installation], paths: &Paths) -> HashMap<String, Vec<String>> {
+    let mut vars: HashMap<&str, OrderedSet<PathBuf>> = HashMap::new();
+
+    for (key, _, _, path) in dirs(installations, paths) {
+        vars.entry(key).or_insert_with(OrderedSet::new).add(path);
+    }
 
     // don’t break `man`
     #[cfg(unix)]
-    if vars.contains_key(&EnvKey::Manpath) {
-        vars.get_mut(&EnvKey::Manpath)
-            .unwrap()
-            .add(PathBuf::from_str("/usr/share/man").unwrap());
+    if let Some(set) = vars.get_mut("MANPATH") {
+        set.add(PathBuf::from_str("/usr/share/man").unwrap());
     }
 
     // https://github.com/pkgxdev/libpkgx/issues/70
     #[cfg(unix)]
-    if vars.contains_key(&EnvKey::XdgDataDirs) {
-        let set = vars.get_mut(&EnvKey::XdgDataDirs).unwrap();
+    if let Some(set) = vars.get_mut("XDG_DATA_DIRS") {
         set.add(PathBuf::from_str("/usr/local/share").unwrap());
         set.add(PathBuf::from_str("/usr/share").unwrap());
     }
@@ -69,41 +147,12 @@ pub fn map(installations: &Vec<Installation>) -> HashMap<String, Vec<String>> {
             .iter()
             .map(|p| p.to_string_lossy().to_string())
             .collect();
-        rv.insert(key.as_ref().to_string(), set);
+        rv.insert(key.to_string(), set);
     }
     rv
 }
 
 use rusqlite::Connection;
-use strum::IntoEnumIterator;
-use strum_macros::{AsRefStr, EnumIter, EnumString};
-
-#[derive(Debug, EnumString, AsRefStr, PartialEq, Eq, Hash, Clone, EnumIter)]
-#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
-enum EnvKey {
-    Path,
-    Manpath,
-    PkgConfigPath,
-    #[cfg(unix)]
-    LibraryPath,
-    #[cfg(unix)]
-    LdLibraryPath,
-    #[cfg(unix)]
-    Cpath,
-    XdgDataDirs,
-    CmakePrefixPath,
-    #[cfg(target_os = "macos")]
-    DyldFallbackLibraryPath,
-    SslCertFile,
-    #[cfg(unix)]
-    Ldflags,
-    PkgxDir,
-    AclocalPath,
-    #[cfg(windows)]
-    Lib,
-    #[cfg(windows)]
-    Include,
-}
 
 struct OrderedSet<T: Eq + std::hash::Hash + Clone> {
     items: Vec<T>,
@@ -125,128 +174,232 @@ impl<T: Eq + std::hash::Hash + Clone> OrderedSet<T> {
     }
 }
 
-fn suffixes(key: &EnvKey) -> Option<Vec<&'static str>> {
-    match key {
-        EnvKey::Path => Some(vec!["bin", "sbin"]),
-        EnvKey::Manpath => Some(vec!["man", "share/man"]),
-        EnvKey::PkgConfigPath => Some(vec!["share/pkgconfig", "lib/pkgconfig"]),
-        EnvKey::XdgDataDirs => Some(vec!["share"]),
-        EnvKey::AclocalPath => Some(vec!["share/aclocal"]),
-        #[cfg(unix)]
-        EnvKey::LibraryPath | EnvKey::LdLibraryPath => Some(vec!["lib", "lib64"]),
-        #[cfg(target_os = "macos")]
-        EnvKey::DyldFallbackLibraryPath => Some(vec!["lib", "lib64"]),
-        #[cfg(unix)]
-        EnvKey::Cpath => Some(vec!["include"]),
-        EnvKey::CmakePrefixPath | EnvKey::SslCertFile | EnvKey::PkgxDir => None,
-        #[cfg(unix)]
-        EnvKey::Ldflags => None,
-        #[cfg(windows)]
-        EnvKey::Lib => Some(vec!["lib"]),
-        #[cfg(windows)]
-        EnvKey::Include => Some(vec!["include"]),
-    }
+/// What a variable becomes in terms of its parent value, ie. what it is
+/// already set to (if anything).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnvValue {
+    /// used if the parent value is unset or empty
+    pub default: Option<String>,
+    /// text to put before the parent value and the separator between them
+    pub prepend: Option<(String, String)>,
+    /// the separator and text to put after the parent value
+    pub append: Option<(String, String)>,
 }
 
-pub fn mix(input: HashMap<String, Vec<String>>) -> HashMap<PlatformCaseAwareEnvKey, String> {
-    let mut rv: HashMap<PlatformCaseAwareEnvKey, String> = HashMap::new();
+// what may separate a runtime value from `$KEY`, eg. `:` or `; `
+const SEPARATORS: &[char] = &[':', ';', ',', ' '];
+
+impl EnvValue {
+    /// Parses a pantry `runtime.env` value for `key`. Only `$KEY`, `${KEY}` or
+    /// `${KEY:-default}` (once) mean anything, a value that doesn’t refer to
+    /// the parent value is a default.
+    pub fn parse(key: &str, value: &str) -> Self {
+        let Some((before, default, after)) = split_at_parent(key, value) else {
+            return Self {
+                default: Some(value.to_string()),
+                ..Self::default()
+            };
+        };
+
+        let text = before.trim_end_matches(SEPARATORS);
+        let prepend =
+            (!text.is_empty()).then(|| (text.to_string(), before[text.len()..].to_string()));
+        let text = after.trim_start_matches(SEPARATORS);
+        let sep = &after[..after.len() - text.len()];
+        let append = (!text.is_empty()).then(|| (sep.to_string(), text.to_string()));
 
-    for (key, value) in std::env::vars() {
-        rv.insert(construct_platform_case_aware_env_key(key), value);
+        Self {
+            default: default.map(str::to_string),
+            prepend,
+            append,
+        }
     }
 
-    for (key, value) in input.iter() {
-        let key = &construct_platform_case_aware_env_key(key.clone());
-        if let Some(values) = rv.get(key) {
-            rv.insert(key.clone(), format!("{}{}{}", value.join(SEP), SEP, values));
-        } else {
-            rv.insert(key.clone(), value.join(SEP));
+    /// Paths ahead of whatever is there.
+    pub fn prepend_paths(paths: &[String]) -> Self {
+        Self {
+            prepend: Some((paths.join(SEP), SEP.to_string())),
+            ..Self::default()
         }
     }
 
-    rv
+    /// `other` applied to the result of this.
+    pub fn then(&mut self, other: Self) {
+        if let Some(default) = other.default {
+            // we set it already or something has set a default first
+            if self.prepend.is_none() && self.append.is_none() && self.default.is_none() {
+                self.default = Some(default);
+            }
+        }
+        if let Some((text, sep)) = other.prepend {
+            self.prepend = Some(match self.prepend.take() {
+                Some((ours, our_sep)) => (format!("{}{}{}", text, sep, ours), our_sep),
+                None => (text, sep),
+            });
+        }
+        if let Some((sep, text)) = other.append {
+            self.append = Some(match self.append.take() {
+                Some((our_sep, ours)) => (our_sep, format!("{}{}{}", ours, sep, text)),
+                None => (sep, text),
+            });
+        }
+    }
+
+    /// The value given the `parent` value. Separators are only added between
+    /// things that aren’t empty.
+    pub fn eval(&self, parent: Option<&str>) -> String {
+        let mut value = match (parent, &self.default) {
+            (Some(parent), _) if !parent.is_empty() => parent.to_string(),
+            (_, Some(default)) => default.clone(),
+            _ => String::new(),
+        };
+        if let Some((text, sep)) = &self.prepend {
+            value = if value.is_empty() {
+                text.clone()
+            } else {
+                format!("{}{}{}", text, sep, value)
+            };
+        }
+        if let Some((sep, text)) = &self.append {
+            value = if value.is_empty() {
+                text.clone()
+            } else {
+                format!("{}{}{}", value, sep, text)
+            };
+        }
+        value
+    }
 }
 
-pub fn mix_runtime(
-    input: &HashMap<PlatformCaseAwareEnvKey, String>,
+// either side of the first `$KEY`, `${KEY}` or `${KEY:-default}` and the default
+fn split_at_parent<'a>(key: &str, value: &'a str) -> Option<(&'a str, Option<&'a str>, &'a str)> {
+    let braced = format!("${{{}}}", key);
+    let defaulted = format!("${{{}:-", key);
+    let bare = format!("${}", key);
+    let mut from = 0;
+    while let Some(i) = value[from..].find('$').map(|i| i + from) {
+        let rest = &value[i..];
+        if rest.starts_with(&braced) {
+            return Some((&value[..i], None, &value[i + braced.len()..]));
+        }
+        if rest.starts_with(&defaulted) {
+            let start = i + defaulted.len();
+            if let Some(end) = value[start..].find('}').map(|end| end + start) {
+                return Some((&value[..i], Some(&value[start..end]), &value[end + 1..]));
+            }
+        }
+        if rest.starts_with(&bare) {
+            let end = i + bare.len();
+            // `$KEY` but not `$KEYS`
+            if !value[end..].starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+                return Some((&value[..i], None, &value[end..]));
+            }
+        }
+        from = i + 1;
+    }
+    None
+}
+
+/// What `installations` do to the environment: their paths (see `map`) and
+/// then each one’s pantry `runtime.env`.
+pub fn runtime(
     installations: &Vec<Installation>,
     conn: &Connection,
-) -> Result<HashMap<PlatformCaseAwareEnvKey, String>, Box<dyn Error>> {
-    let mut output: HashMap<PlatformCaseAwareEnvKey, String> = input
-        .iter()
-        .map(|(k, v)| (k.clone(), format!("{}{}${}", v, SEP, k)))
+    config: &Config,
+) -> Result<HashMap<PlatformCaseAwareEnvKey, EnvValue>, Error> {
+    let paths = Paths::load(installations, conn, config)?;
+    let mut rv: HashMap<PlatformCaseAwareEnvKey, EnvValue> = map(installations, &paths)
+        .into_iter()
+        .map(|(key, paths)| {
+            let key = construct_platform_case_aware_env_key(key);
+            (key, EnvValue::prepend_paths(&paths))
+        })
         .collect();
 
-    for installation in installations.clone() {
+    for installation in installations {
         let runtime_env =
             crate::pantry_db::runtime_env_for_project(&installation.pkg.project, conn)?;
-        for (key, runtime_value) in runtime_env {
-            let runtime_value = expand_moustaches(&runtime_value, &installation, installations);
-            let insert_key = construct_platform_case_aware_env_key(key.clone());
-            let new_value = if let Some(curr_value) = output.get(&insert_key) {
-                if runtime_value.contains(&format!("${}", key)) {
-                    runtime_value.replace(&format!("${}", key), curr_value)
-                } else {
-                    // parent env overrides runtime env if the runtime env
-                    // has no capacity to include the parent env
-                    curr_value.clone()
-                }
-            } else if runtime_value.contains(&format!("${}", key)) {
-                runtime_value
-            } else {
-                format!("${{{}:-{}}}", key, runtime_value)
-            };
-            output.insert(insert_key, new_value);
+        for (key, value) in runtime_env {
+            let value = moustache::render(&value, installation, installations, config)?;
+            let value = EnvValue::parse(&key, &value);
+            rv.entry(construct_platform_case_aware_env_key(key))
+                .or_default()
+                .then(value);
         }
     }
 
-    Ok(output)
+    Ok(rv)
+}
+
+/// What put something in a variable, see `sources`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Source {
+    /// a directory of an installation, eg. its `bin` for `PATH`
+    Path {
+        pkg: Package,
+        suffix: String,
+        path: String,
+    },
+    /// a line of the project’s `runtime.env` as the pantry has it
+    Runtime { pkg: Package, line: String },
+    /// pkgx itself, eg. `PKGX_LVL`
+    Pkgx,
 }
 
-pub fn expand_moustaches(input: &str, pkg: &Installation, deps: &Vec<Installation>) -> String {
-    let mut output = input.to_string();
-
-    if output.starts_with("${{") {
-        output.replace_range(..1, "");
-    }
-
-    output = output.replace("{{prefix}}", &pkg.path.to_string_lossy());
-    output = output.replace("{{version}}", &format!("{}", &pkg.pkg.version));
-    output = output.replace("{{version.major}}", &format!("{}", pkg.pkg.version.major));
-    output = output.replace("{{version.minor}}", &format!("{}", pkg.pkg.version.minor));
-    output = output.replace("{{version.patch}}", &format!("{}", pkg.pkg.version.patch));
-    output = output.replace(
-        "{{version.marketing}}",
-        &format!("{}.{}", pkg.pkg.version.major, pkg.pkg.version.minor),
-    );
-
-    for dep in deps {
-        let prefix = format!("deps.{}", dep.pkg.project);
-        output = output.replace(
-            &format!("{{{{{}.prefix}}}}", prefix),
-            &dep.path.to_string_lossy(),
-        );
-        output = output.replace(
-            &format!("{{{{{}.version}}}}", prefix),
-            &format!("{}", &dep.pkg.version),
-        );
-        output = output.replace(
-            &format!("{{{{{}.version.major}}}}", prefix),
-            &format!("{}", dep.pkg.version.major),
-        );
-        output = output.replace(
-            &format!("{{{{{}.version.minor}}}}", prefix),
-            &format!("{}", dep.pkg.version.minor),
-        );
-        output = output.replace(
-            &format!("{{{{{}.version.patch}}}}", prefix),
-            &format!("{}", dep.pkg.version.patch),
-        );
-        output = output.replace(
-            &format!("{{{{{}.version.marketing}}}}", prefix),
-            &format!("{}.{}", dep.pkg.version.major, dep.pkg.version.minor),
-        );
-    }
-
-    output
+/// What put each of `runtime`’s variables there, in the order it applies them.
+pub fn sources(
+    installations: &Vec<Installation>,
+    conn: &Connection,
+    config: &Config,
+) -> Result<HashMap<PlatformCaseAwareEnvKey, Vec<Source>>, Error> {
+    let paths = Paths::load(installations, conn, config)?;
+    let mut rv: HashMap<PlatformCaseAwareEnvKey, Vec<Source>> = HashMap::new();
+
+    for (key, installation, suffix, path) in dirs(installations, &paths) {
+        let path = path.to_string_lossy().to_string();
+        let sources = rv
+            .entry(construct_platform_case_aware_env_key(key.to_string()))
+            .or_default();
+        if !sources
+            .iter()
+            .any(|source| matches!(source, Source::Path { path: p, .. } if *p == path))
+        {
+            sources.push(Source::Path {
+                pkg: installation.pkg.clone(),
+                suffix: suffix.to_string(),
+                path,
+            });
+        }
+    }
+
+    for installation in installations {
+        let runtime_env =
+            crate::pantry_db::runtime_env_for_project(&installation.pkg.project, conn)?;
+        for (key, value) in runtime_env {
+            let line = format!("{}={}", key, value);
+            rv.entry(construct_platform_case_aware_env_key(key))
+                .or_default()
+                .push(Source::Runtime {
+                    pkg: installation.pkg.clone(),
+                    line,
+                });
+        }
+    }
+
+    Ok(rv)
+}
+
+/// Our environment with `values` applied, ie. what to run things with.
+pub fn mix(
+    values: &HashMap<PlatformCaseAwareEnvKey, EnvValue>,
+) -> HashMap<PlatformCaseAwareEnvKey, String> {
+    let mut rv: HashMap<PlatformCaseAwareEnvKey, String> = std::env::vars()
+        .map(|(key, value)| (construct_platform_case_aware_env_key(key), value))
+        .collect();
+    for (key, value) in values {
+        let value = value.eval(rv.get(key).map(String::as_str));
+        rv.insert(key.clone(), value);
+    }
+    rv
 }
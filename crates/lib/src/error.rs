@@ -0,0 +1,127 @@
+use std::{fmt, io, path::PathBuf};
+
+use crate::{
+    hydrate::Conflict,
+    types::{Package, PackageReq},
+};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub enum Error {
+    /// nothing satisfies the requirement, either installed or in the inventory
+    NotFound(PackageReq),
+    /// the pantry restricts the project to `platforms:` that don’t include ours
+    Unsupported {
+        project: String,
+        platform: String,
+    },
+    /// there is no such program in the provided paths
+    ProgramNotFound(String),
+    /// two requirements for the same project cannot both be satisfied
+    Conflict(Box<Conflict>),
+    Network(reqwest::Error),
+    /// a downloaded bottle does not match its published or pinned sha256
+    Integrity {
+        pkg: Box<Package>,
+        url: String,
+        expected: String,
+        actual: String,
+    },
+    Db(rusqlite::Error),
+    /// we could not acquire the lock for this path
+    Locked(PathBuf),
+    /// something was needed from the network but `Config::offline` is set
+    Offline(Vec<String>),
+    /// a lockfile is invalid or is for another platform
+    Lockfile(String),
+    /// eg. an invalid pkgspec, version or pantry entry
+    Parse(String),
+    Io(io::Error),
+    Other(String),
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Network(err) => Some(err),
+            Error::Db(err) => Some(err),
+            Error::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotFound(pkg) => write!(f, "not found: {}", pkg),
+            Error::Unsupported { project, platform } => {
+                write!(f, "{} is not available for {}", project, platform)
+            }
+            Error::ProgramNotFound(cmd) => write!(f, "cmd not found: {}", cmd),
+            Error::Conflict(conflict) => write!(f, "{}", conflict),
+            Error::Network(err) => write!(f, "network error: {}", err),
+            Error::Integrity {
+                pkg,
+                url,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "integrity check failed for {}: {} has sha256 {} but expected {}",
+                pkg, url, actual, expected
+            ),
+            Error::Db(err) => write!(f, "db error: {}", err),
+            Error::Locked(path) => write!(f, "couldn’t lock {}", path.display()),
+            Error::Offline(missing) => {
+                write!(f, "not available offline: {}", missing.join(", "))
+            }
+            Error::Lockfile(msg) | Error::Parse(msg) | Error::Other(msg) => write!(f, "{}", msg),
+            Error::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Network(err)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(err: rusqlite::Error) -> Self {
+        Error::Db(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<tokio::task::JoinError> for Error {
+    fn from(err: tokio::task::JoinError) -> Self {
+        Error::Other(err.to_string())
+    }
+}
+
+// libsemverator reports invalid versions and ranges with anyhow
+impl From<anyhow::Error> for Error {
+    fn from(err: anyhow::Error) -> Self {
+        Error::Parse(err.to_string())
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(err: serde_yaml::Error) -> Self {
+        Error::Parse(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Parse(err.to_string())
+    }
+}
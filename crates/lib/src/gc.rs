@@ -0,0 +1,112 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use fs2::FileExt;
+
+use crate::{
+    cellar,
+    config::Config,
+    error::Error,
+    install::Receipt,
+    types::{Installation, PackageReq},
+};
+
+/// Records that these installations were just used.
+///
+/// We use the mtime of a mark next to the installation for this, which is
+/// best effort. Not the installation’s own, tar sets that to the bottle’s.
+pub fn mark_used(installations: &[Installation]) {
+    for installation in installations {
+        let mark = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(mark(installation));
+        if let Ok(mark) = mark {
+            let _ = mark.set_modified(SystemTime::now());
+        }
+    }
+}
+
+// eg. `~/.pkgx/nodejs.org/.v20.1.0.used`, as `Receipt::path`
+fn mark(installation: &Installation) -> PathBuf {
+    let name = installation.path.file_name().unwrap_or_default();
+    installation
+        .path
+        .with_file_name(format!(".{}.used", name.to_string_lossy()))
+}
+
+/// Installations not reachable from `roots`, from anything used within `keep`,
+/// or from the dependencies of either.
+pub async fn garbage<F>(
+    roots: &[PackageReq],
+    keep: Duration,
+    config: &Config,
+    get_deps: F,
+) -> Result<Vec<Installation>, Error>
+where
+    F: Fn(&str) -> Result<Vec<PackageReq>, Error>,
+{
+    let all = cellar::ls_all(config).await?;
+
+    let mut stack: Vec<Installation> = all
+        .iter()
+        .filter(|installation| used_within(installation, keep))
+        .cloned()
+        .collect();
+    for req in roots {
+        if let Some(installation) = cellar::resolve(req, config).await? {
+            stack.push(installation);
+        }
+    }
+
+    let mut reachable: HashSet<PathBuf> = HashSet::new();
+    while let Some(installation) = stack.pop() {
+        if !reachable.insert(installation.path.clone()) {
+            continue;
+        }
+        for dep in get_deps(&installation.pkg.project)? {
+            if let Some(dep) = cellar::resolve(&dep, config).await? {
+                stack.push(dep);
+            }
+        }
+    }
+
+    Ok(all
+        .into_iter()
+        .filter(|installation| !reachable.contains(&installation.path))
+        .collect())
+}
+
+/// Deletes an installation under the same lock `install` uses and repairs
+/// the shelf’s version symlinks.
+pub async fn remove(installation: &Installation, config: &Config) -> Result<(), Error> {
+    let lockfile = cellar::lock(&installation.pkg.project, config).await?;
+
+    if installation.path.is_dir() {
+        fs::remove_dir_all(&installation.path)?;
+    }
+    let _ = fs::remove_file(Receipt::path(installation));
+    let _ = fs::remove_file(mark(installation));
+
+    #[cfg(not(windows))]
+    crate::install::repair_symlinks(&installation.pkg.project, config).await?;
+
+    FileExt::unlock(&lockfile)?;
+
+    Ok(())
+}
+
+// without a mark it was installed before we kept them, it may well be in use
+fn used_within(installation: &Installation, keep: Duration) -> bool {
+    fs::metadata(mark(installation))
+        .and_then(|metadata| metadata.modified())
+        // a future mtime is clock skew, so be conservative
+        .map_or(true, |mtime| {
+            mtime.elapsed().map_or(true, |elapsed| elapsed < keep)
+        })
+}
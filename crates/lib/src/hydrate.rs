@@ -1,13 +1,45 @@
-use crate::types::PackageReq;
-use libsemverator::range::Range as VersionReq;
-use std::collections::{HashMap, HashSet};
-use std::error::Error;
+use crate::error::Error;
+use crate::types::{Package, PackageReq};
+use libsemverator::semver::Semver as Version;
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::future::Future;
+
+/// Which projects may be in an environment at several versions at once.
+///
+/// Everything else must resolve to a single version that satisfies every
+/// requirement on it.
+#[derive(Debug, Clone)]
+pub struct Policy {
+    pub multi_version: HashSet<String>,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            // data only, so there is nothing to clash, eg:
+            // https://github.com/pkgxdev/pantry/issues/4104
+            // https://github.com/pkgxdev/pkgx/issues/899
+            multi_version: HashSet::from(["unicode.org".to_string()]),
+        }
+    }
+}
+
+impl Policy {
+    pub fn allows_multiple(&self, project: &str) -> bool {
+        self.multi_version.contains(project)
+    }
+}
 
 #[derive(Clone)]
 struct Node {
     parent: Option<Box<Node>>,
     pkg: PackageReq,
     children: HashSet<String>,
+    /// every requirement path that has constrained this node, from a requested package down
+    via: Vec<Vec<PackageReq>>,
 }
 
 impl Node {
@@ -16,9 +48,18 @@ impl Node {
             parent,
             pkg,
             children: HashSet::new(),
+            via: vec![],
         }
     }
 
+    /// The path that first brought us to this node.
+    fn path(&self) -> Vec<PackageReq> {
+        self.via
+            .first()
+            .cloned()
+            .unwrap_or_else(|| vec![self.pkg.clone()])
+    }
+
     fn count(&self) -> usize {
         let mut count = 0;
         let mut node = self.parent.as_ref();
@@ -31,47 +72,61 @@ impl Node {
 }
 
 /// Hydrates dependencies and returns a topologically sorted list of packages.
+///
+/// Projects the `policy` allows at several versions appear once per
+/// requirement that doesn’t intersect with the others.
 pub async fn hydrate<F>(
     input: &Vec<PackageReq>,
+    policy: &Policy,
     get_deps: F,
-) -> Result<Vec<PackageReq>, Box<dyn Error>>
+) -> Result<Vec<PackageReq>, Error>
 where
-    F: Fn(String) -> Result<Vec<PackageReq>, Box<dyn Error>>,
+    F: Fn(String) -> Result<Vec<PackageReq>, Error>,
 {
-    let dry = condense(input);
+    let dry = condense(input)?;
     let mut graph: HashMap<String, Box<Node>> = HashMap::new();
     let mut stack: Vec<Box<Node>> = vec![];
-    let mut additional_unicodes: Vec<VersionReq> = vec![];
+    let mut additional: Vec<PackageReq> = vec![];
 
     for pkg in dry.iter() {
         let node = graph
             .entry(pkg.project.clone())
             .or_insert_with(|| Box::new(Node::new(pkg.clone(), None)));
-        node.pkg.constraint = intersect_constraints(&node.pkg.constraint, &pkg.constraint)
-            .map_err(|e| format!("{} for {}", e, pkg.project))?;
+        node.via.push(vec![pkg.clone()]);
         stack.push(node.clone());
     }
 
     while let Some(mut current) = stack.pop() {
         for child_pkg in get_deps(current.pkg.project.clone())? {
+            let mut path = current.path();
+            path.push(child_pkg.clone());
+
             let child_node = graph
                 .entry(child_pkg.project.clone())
                 .or_insert_with(|| Box::new(Node::new(child_pkg.clone(), Some(current.clone()))));
-            let intersection =
-                intersect_constraints(&child_node.pkg.constraint, &child_pkg.constraint);
-            if let Ok(constraint) = intersection {
-                child_node.pkg.constraint = constraint;
-                current.children.insert(child_node.pkg.project.clone());
-                stack.push(child_node.clone());
-            } else if child_pkg.project == "unicode.org" {
-                // we handle unicode.org for now to allow situations like:
-                // https://github.com/pkgxdev/pantry/issues/4104
-                // https://github.com/pkgxdev/pkgx/issues/899
-                additional_unicodes.push(child_pkg.constraint);
-            } else {
-                return Err(
-                    format!("{} for {}", intersection.unwrap_err(), child_pkg.project).into(),
-                );
+            match child_node.pkg.constraint.intersect(&child_pkg.constraint) {
+                Ok(constraint) => {
+                    child_node.pkg.constraint = constraint;
+                    child_node.via.push(path);
+                    current.children.insert(child_node.pkg.project.clone());
+                    stack.push(child_node.clone());
+                }
+                Err(_) if policy.allows_multiple(&child_pkg.project) => {
+                    if !additional.iter().any(|pkg| {
+                        pkg.project == child_pkg.project
+                            && pkg.constraint.raw == child_pkg.constraint.raw
+                    }) {
+                        additional.push(child_pkg);
+                    }
+                }
+                Err(_) => {
+                    return Err(Error::Conflict(Box::new(Conflict {
+                        project: child_pkg.project.clone(),
+                        a: clashing_path(&child_node.via, &child_pkg)
+                            .unwrap_or_else(|| child_node.path()),
+                        b: path,
+                    })));
+                }
             }
         }
     }
@@ -80,33 +135,318 @@ where
     pkgs.sort_by_key(|node| node.count());
     let mut pkgs: Vec<PackageReq> = pkgs.into_iter().map(|node| node.pkg.clone()).collect();
 
-    // see above explanation
-    for constraint in additional_unicodes {
-        let pkg = PackageReq {
-            project: "unicode.org".to_string(),
-            constraint,
+    pkgs.extend(additional);
+
+    Ok(pkgs)
+}
+
+/// Supplies `solve` with the versions and dependencies of packages.
+pub trait Provider {
+    /// Versions already installed, these are preferred.
+    fn installed(&self, project: &str) -> impl Future<Output = Result<Vec<Version>, Error>>;
+
+    /// Every version that could be installed, only asked for when nothing
+    /// installed works out.
+    fn available(&self, project: &str) -> impl Future<Output = Result<Vec<Version>, Error>>;
+
+    fn deps(&self, pkg: &Package) -> Result<Vec<PackageReq>, Error>;
+}
+
+/// A package the solver settled on.
+#[derive(Debug, Clone)]
+pub struct Pick {
+    pub pkg: Package,
+    /// the intersection of every requirement `pkg` was picked to satisfy
+    pub req: PackageReq,
+    /// requirement paths that led here, each from a requested package down
+    via: Vec<Vec<PackageReq>>,
+}
+
+#[derive(Clone, Default)]
+struct State {
+    picks: Vec<Pick>,
+    /// requirement paths yet to be satisfied, the requirement is the last element
+    pending: VecDeque<Vec<PackageReq>>,
+}
+
+/// A choice we can revisit: the state before it was made and the versions
+/// not yet tried.
+struct Decision {
+    state: State,
+    path: Vec<PackageReq>,
+    candidates: VecDeque<Version>,
+    tried: Vec<Version>,
+    /// the deps of each version tried, another version with the same deps
+    /// can only fail the same way
+    tried_deps: Vec<Vec<String>>,
+    fetched: bool,
+}
+
+/// Picks concrete versions for `input` and everything it depends on.
+///
+/// Dependencies may differ by version, so when a requirement can’t be met we
+/// backtrack and try older versions of the packages picked before it.
+/// Installed versions are preferred over newer ones that would need
+/// installing. Returns picks ordered shallowest first.
+pub async fn solve<P: Provider>(
+    input: &[PackageReq],
+    policy: &Policy,
+    provider: &P,
+) -> Result<Vec<Pick>, Error> {
+    let mut state = State {
+        picks: vec![],
+        pending: input.iter().map(|req| vec![req.clone()]).collect(),
+    };
+    let mut decisions: Vec<Decision> = vec![];
+    // the first failure is the most useful to report, later ones are
+    // consequences of us backtracking
+    let mut failure: Option<Error> = None;
+
+    loop {
+        if let Some(path) = state.pending.pop_front() {
+            let req = path.last().unwrap().clone();
+            let picked: Vec<usize> = (0..state.picks.len())
+                .filter(|&i| state.picks[i].pkg.project == req.project)
+                .collect();
+
+            if let Some(&i) = picked
+                .iter()
+                .find(|&&i| req.constraint.satisfies(&state.picks[i].pkg.version))
+            {
+                let pick = &mut state.picks[i];
+                if let Ok(constraint) = pick.req.constraint.intersect(&req.constraint) {
+                    pick.req.constraint = constraint;
+                }
+                pick.via.push(path);
+                continue;
+            }
+
+            if let Some(&i) = picked.first() {
+                if !policy.allows_multiple(&req.project) {
+                    let via = &state.picks[i].via;
+                    let a = clashing_path(via, &req).unwrap_or_else(|| via[0].clone());
+                    let culprits = [&a[..], &path[..]].concat();
+                    failure.get_or_insert_with(|| {
+                        Error::Conflict(Box::new(Conflict {
+                            project: req.project.clone(),
+                            a,
+                            b: path,
+                        }))
+                    });
+                    state = match backtrack(&mut decisions, &culprits, Some(&req.project), provider)
+                        .await?
+                    {
+                        Some(state) => state,
+                        None => return Err(failure.unwrap()),
+                    };
+                    continue;
+                }
+            }
+
+            let mut candidates: Vec<Version> = provider
+                .installed(&req.project)
+                .await?
+                .into_iter()
+                .filter(|version| req.constraint.satisfies(version))
+                .collect();
+            candidates.sort_by(|a, b| b.cmp(a));
+
+            let mut decision = Decision {
+                state,
+                path,
+                candidates: candidates.into(),
+                tried: vec![],
+                tried_deps: vec![],
+                fetched: false,
+            };
+            state = match next(&mut decision, None, provider).await? {
+                Some(state) => {
+                    decisions.push(decision);
+                    state
+                }
+                None => {
+                    failure.get_or_insert(Error::NotFound(req));
+                    match backtrack(&mut decisions, &decision.path, None, provider).await? {
+                        Some(state) => state,
+                        None => return Err(failure.unwrap()),
+                    }
+                }
+            };
+        } else {
+            let mut picks = state.picks;
+            picks.sort_by_key(|pick| pick.via.iter().map(Vec::len).min());
+            return Ok(picks);
+        }
+    }
+}
+
+/// Unwinds to the most recent decision with a version left to try.
+///
+/// Decisions about packages that aren’t on the `culprits` paths didn’t lead to
+/// the failure so trying their other versions can’t fix it, we jump past them.
+/// A culprit that runs out of versions adds the path that required it to the
+/// culprits, once there are none left the failure stands. `conflict` is the
+/// project whose requirements clashed, if any.
+async fn backtrack<P: Provider>(
+    decisions: &mut Vec<Decision>,
+    culprits: &[PackageReq],
+    conflict: Option<&str>,
+    provider: &P,
+) -> Result<Option<State>, Error> {
+    let mut culprits: HashSet<String> = culprits.iter().map(|req| req.project.clone()).collect();
+    while let Some(mut decision) = decisions.pop() {
+        if !culprits.contains(&decision.path.last().unwrap().project) {
+            continue;
+        }
+        if let Some(state) = next(&mut decision, conflict, provider).await? {
+            decisions.push(decision);
+            return Ok(Some(state));
+        }
+        culprits.extend(decision.path.iter().map(|req| req.project.clone()));
+    }
+    Ok(None)
+}
+
+/// The state that results from picking the next candidate of `decision`.
+///
+/// Unless its project is the `conflict` (then its version matters) versions
+/// with the same deps as one already tried are passed over.
+async fn next<P: Provider>(
+    decision: &mut Decision,
+    conflict: Option<&str>,
+    provider: &P,
+) -> Result<Option<State>, Error> {
+    let req = decision.path.last().unwrap().clone();
+
+    // passed over only for this failure, another may need their version
+    let mut passed: Vec<Version> = vec![];
+    let picked = loop {
+        let Some(version) = decision.candidates.pop_front() else {
+            if decision.fetched {
+                break None;
+            }
+            decision.fetched = true;
+            let mut available: Vec<Version> = provider
+                .available(&req.project)
+                .await?
+                .into_iter()
+                .filter(|version| req.constraint.satisfies(version))
+                .filter(|version| !decision.tried.contains(version) && !passed.contains(version))
+                .collect();
+            available.sort_by(|a, b| b.cmp(a));
+            decision.candidates = available.into();
+            continue;
+        };
+        let pkg = Package {
+            project: req.project.clone(),
+            version,
         };
-        pkgs.push(pkg);
+        let deps = provider.deps(&pkg)?;
+        let mut key: Vec<String> = deps.iter().map(|dep| dep.to_string()).collect();
+        key.sort();
+        if conflict != Some(req.project.as_str()) && decision.tried_deps.contains(&key) {
+            passed.push(pkg.version);
+            continue;
+        }
+        decision.tried.push(pkg.version.clone());
+        decision.tried_deps.push(key);
+        break Some((pkg, deps));
+    };
+    for version in passed.into_iter().rev() {
+        decision.candidates.push_front(version);
     }
+    let Some((pkg, deps)) = picked else {
+        return Ok(None);
+    };
 
-    Ok(pkgs)
+    let mut state = decision.state.clone();
+    for dep in deps {
+        let mut path = decision.path.clone();
+        path.push(dep);
+        state.pending.push_back(path);
+    }
+    state.picks.push(Pick {
+        pkg,
+        req: req.clone(),
+        via: vec![decision.path.clone()],
+    });
+    Ok(Some(state))
+}
+
+/// Which of `paths` clashes with `req`, if any one does alone rather than only
+/// with the intersection of them all.
+fn clashing_path(paths: &[Vec<PackageReq>], req: &PackageReq) -> Option<Vec<PackageReq>> {
+    paths
+        .iter()
+        .find(|path| {
+            path.last()
+                .is_some_and(|last| last.constraint.intersect(&req.constraint).is_err())
+        })
+        .cloned()
 }
 
 /// Condenses a list of `PackageRequirement` by intersecting constraints for duplicates.
-fn condense(pkgs: &Vec<PackageReq>) -> Vec<PackageReq> {
+fn condense(pkgs: &Vec<PackageReq>) -> Result<Vec<PackageReq>, Error> {
     let mut out: Vec<PackageReq> = vec![];
     for pkg in pkgs {
         if let Some(existing) = out.iter_mut().find(|p| p.project == pkg.project) {
-            existing.constraint = intersect_constraints(&existing.constraint, &pkg.constraint)
-                .expect("Failed to intersect constraints");
+            existing.constraint = existing
+                .constraint
+                .intersect(&pkg.constraint)
+                .map_err(|_| {
+                    Error::Conflict(Box::new(Conflict {
+                        project: pkg.project.clone(),
+                        a: vec![existing.clone()],
+                        b: vec![pkg.clone()],
+                    }))
+                })?;
         } else {
             out.push(pkg.clone());
         }
     }
-    out
+    Ok(out)
 }
 
-/// Intersects two version constraints.
-fn intersect_constraints(a: &VersionReq, b: &VersionReq) -> Result<VersionReq, Box<dyn Error>> {
-    a.intersect(b).map_err(|e| e.into())
+/// Two requirements for the same project that cannot both be satisfied.
+///
+/// `a` and `b` are the requirement paths that led to the clash, each starting
+/// at a requested package and ending with the requirement on `project`.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub project: String,
+    pub a: Vec<PackageReq>,
+    pub b: Vec<PackageReq>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = |path: &Vec<PackageReq>| {
+            path.iter()
+                .map(|req| req.to_string())
+                .collect::<Vec<_>>()
+                .join(" → ")
+        };
+        write!(
+            f,
+            "conflicting constraints for {}: {} vs {}",
+            self.project,
+            path(&self.a),
+            path(&self.b)
+        )
+    }
+}
+
+impl Serialize for Conflict {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let path = |path: &Vec<PackageReq>| -> Vec<String> {
+            path.iter().map(|req| req.to_string()).collect()
+        };
+        let mut state = serializer.serialize_struct("Conflict", 2)?;
+        state.serialize_field("project", &self.project)?;
+        state.serialize_field("paths", &[path(&self.a), path(&self.b)])?;
+        state.end()
+    }
 }
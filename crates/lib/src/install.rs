@@ -1,11 +1,13 @@
 use async_compression::tokio::bufread::XzDecoder;
 use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
-    error::Error,
-    fs::{self, OpenOptions},
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
 };
 use tempfile::tempdir_in;
-use tokio::task;
 use tokio_tar::ArchiveBuilder;
 
 // Compatibility trait lets us call `compat()` on a futures::io::AsyncRead
@@ -20,6 +22,8 @@ use crate::{
     cellar,
     client::build_client,
     config::Config,
+    download,
+    error::Error,
     inventory,
     types::{Installation, Package},
 };
@@ -29,40 +33,68 @@ pub enum InstallEvent {
     Progress(u64),     // we downloaded n bytes
 }
 
+/// Where to download a bottle from and the digest it must have, eg. as pinned by a lockfile.
+#[derive(Debug, Clone)]
+pub struct Bottle {
+    pub url: String,
+    pub sha256: String,
+}
+
+/// Where an installation’s bottle came from, kept beside it for diagnostics.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Receipt {
+    pub url: String,
+    /// the dist mirror that served it, unless a lockfile pinned `url`
+    pub mirror: Option<String>,
+    pub sha256: String,
+}
+
+impl Receipt {
+    /// eg. `~/.pkgx/nodejs.org/.v20.1.0.receipt.json`
+    pub fn path(installation: &Installation) -> PathBuf {
+        let name = installation.path.file_name().unwrap_or_default();
+        installation
+            .path
+            .with_file_name(format!(".{}.receipt.json", name.to_string_lossy()))
+    }
+
+    pub fn read(installation: &Installation) -> Option<Self> {
+        let content = fs::read_to_string(Self::path(installation)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    // only for diagnostics, failing to write it is not fatal
+    fn write(&self, installation: &Installation) {
+        if let Ok(content) = serde_json::to_string_pretty(self) {
+            let _ = fs::write(Self::path(installation), content);
+        }
+    }
+}
+
 //TODO set UserAgent
 
 pub async fn install<F>(
     pkg: &Package,
     config: &Config,
-    mut event_callback: Option<F>,
-) -> Result<Installation, Box<dyn Error>>
+    event_callback: Option<F>,
+) -> Result<Installation, Error>
 where
     F: FnMut(InstallEvent) + Send + 'static,
 {
-    let shelf = config.pkgx_dir.join(&pkg.project);
-    fs::create_dir_all(&shelf)?;
+    install_bottle(pkg, None, config, event_callback).await
+}
 
-    #[cfg(windows)]
-    let lockfile = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(shelf.join("lockfile"))?;
-    #[cfg(not(windows))]
-    let lockfile = OpenOptions::new()
-        .read(true) // Open the directory in read-only mode
-        .open(shelf.clone())?;
-
-    task::spawn_blocking({
-        let lockfile = lockfile.try_clone()?;
-        move || {
-            lockfile
-                .lock_exclusive()
-                .expect("unexpected error: install locking failed");
-        }
-    })
-    .await?;
+/// Installs `pkg` from `bottle` if provided, otherwise from the inventory.
+pub async fn install_bottle<F>(
+    pkg: &Package,
+    bottle: Option<&Bottle>,
+    config: &Config,
+    mut event_callback: Option<F>,
+) -> Result<Installation, Error>
+where
+    F: FnMut(InstallEvent) + Send + 'static,
+{
+    let lockfile = cellar::lock(&pkg.project, config).await?;
 
     let dst_path = cellar::dst(pkg, config);
 
@@ -76,29 +108,52 @@ where
         });
     }
 
-    let url = inventory::get_url(pkg, config);
+    if config.offline {
+        FileExt::unlock(&lockfile)?;
+        return Err(Error::Offline(vec![pkg.to_string()]));
+    }
+
     let client = build_client()?;
-    let rsp = client.get(url).send().await?.error_for_status()?;
+    let (rsp, url, mirror, expected) = if let Some(bottle) = bottle {
+        let rsp = download::get(&client, &bottle.url).await?;
+        (rsp, bottle.url.clone(), None, bottle.sha256.clone())
+    } else {
+        let path = inventory::bottle_path(pkg, &config.platform);
+        let (rsp, mirror) =
+            download::get_mirrored(&config.dist_urls, &path, |url| client.get(url)).await?;
+        // the checksum comes from the same mirror as the bottle
+        let url = format!("{}/{}", mirror, path);
+        let expected = fetch_checksum(&url, &client).await?;
+        (rsp, url, Some(mirror), expected)
+    };
 
     let total_size = rsp
         .content_length()
-        .ok_or("Failed to get content length from response")?;
+        .ok_or_else(|| Error::Other("Failed to get content length from response".into()))?;
 
     if let Some(cb) = event_callback.as_mut() {
         cb(InstallEvent::DownloadSize(total_size));
     }
 
-    let stream = rsp.bytes_stream();
+    // survives flaky connections by resuming where it left off
+    let stream = download::resumable(client, url.clone(), rsp, total_size);
+
+    // we hash the raw bytes as they arrive so we needn’t buffer the bottle
+    let hasher = Arc::new(Mutex::new(Sha256::new()));
 
     //TODO we don’t want to add inspect_ok to the stream at all in --silent mode
     //  ^^ but the borrow checker despises us with a venom I can barely articulate if we try
-    let stream = stream.inspect_ok(move |chunk| {
-        if let Some(cb) = event_callback.as_mut() {
-            cb(InstallEvent::Progress(chunk.len() as u64));
+    let stream = stream.inspect_ok({
+        let hasher = hasher.clone();
+        move |chunk| {
+            hasher.lock().unwrap().update(chunk);
+            if let Some(cb) = event_callback.as_mut() {
+                cb(InstallEvent::Progress(chunk.len() as u64));
+            }
         }
     });
 
-    let stream = stream.map_err(futures::io::Error::other).into_async_read();
+    let stream = stream.into_async_read();
     let stream = stream.compat();
 
     // Step 2: Create a XZ decoder
@@ -113,7 +168,26 @@ where
         .build();
     archive.unpack(&temp_dir).await?;
 
-    // Step 5: atomically move from temp dir to installation location
+    // Step 5: verify the checksum before anything reaches the cellar
+    // tar stops reading at its end-of-archive marker so drain the rest of the
+    // download to ensure the digest covers every byte
+    let mut stream = archive
+        .into_inner()
+        .map_err(|_| Error::Other("unexpected error: tar archive still borrowed".into()))?
+        .into_inner();
+    tokio::io::copy(&mut stream, &mut tokio::io::sink()).await?;
+
+    let actual = format!("{:x}", hasher.lock().unwrap().clone().finalize());
+    if actual != expected {
+        return Err(Error::Integrity {
+            pkg: Box::new(pkg.clone()),
+            url,
+            expected,
+            actual,
+        });
+    }
+
+    // Step 6: atomically move from temp dir to installation location
     let partial_path = format!("{}/v{}", pkg.project, pkg.version.raw);
     fs::rename(temp_dir.path().join(&partial_path), &dst_path)?;
 
@@ -122,6 +196,16 @@ where
         pkg: pkg.clone(),
     };
 
+    // tar preserves the bottle’s mtime, but for gc purposes it is “used” now
+    crate::gc::mark_used(std::slice::from_ref(&installation));
+
+    Receipt {
+        url,
+        mirror,
+        sha256: expected,
+    }
+    .write(&installation);
+
     #[cfg(not(windows))]
     symlink(&installation, config).await?;
     // ^^ you need admin privs to symlink on windows (wtf)
@@ -131,14 +215,67 @@ where
     Ok(installation)
 }
 
+// bottles are published with a `sha256sum` style sidecar, eg. `<digest>  v1.2.3.tar.xz`
+pub(crate) async fn fetch_checksum(url: &str, client: &reqwest::Client) -> Result<String, Error> {
+    let rsp = download::get(client, &format!("{}.sha256sum", url)).await?;
+    parse_checksum(rsp, url).await
+}
+
+/// The url of `pkg`’s bottle on the first mirror that has it and its checksum.
+pub(crate) async fn fetch_mirrored_checksum(
+    pkg: &Package,
+    config: &Config,
+    client: &reqwest::Client,
+) -> Result<(String, String), Error> {
+    let path = inventory::bottle_path(pkg, &config.platform);
+    let (rsp, mirror) =
+        download::get_mirrored(&config.dist_urls, &format!("{}.sha256sum", path), |url| {
+            client.get(url)
+        })
+        .await?;
+    let url = format!("{}/{}", mirror, path);
+    let checksum = parse_checksum(rsp, &url).await?;
+    Ok((url, checksum))
+}
+
+async fn parse_checksum(rsp: reqwest::Response, url: &str) -> Result<String, Error> {
+    let body = rsp.text().await?;
+    match body.split_whitespace().next() {
+        Some(digest) if digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Ok(digest.to_lowercase())
+        }
+        _ => Err(Error::Parse(format!(
+            "invalid checksum file: {}.sha256sum",
+            url
+        ))),
+    }
+}
+
 #[cfg(not(windows))]
 use {
     libsemverator::range::Range as VersionReq, libsemverator::semver::Semver as Version,
-    std::collections::VecDeque, std::path::Path, std::path::PathBuf,
+    std::collections::VecDeque, std::path::Path,
 };
 
+/// Removes `vX`, `vX.Y` & `v*` symlinks to versions that no longer exist and
+/// repoints them at the newest remaining installations.
+#[cfg(not(windows))]
+pub(crate) async fn repair_symlinks(project: &str, config: &Config) -> Result<(), Error> {
+    let shelf = config.pkgx_dir.join(project);
+    for entry in fs::read_dir(&shelf)? {
+        let path = entry?.path();
+        if path.is_symlink() && !path.exists() {
+            fs::remove_file(&path)?;
+        }
+    }
+    for installation in cellar::ls(project, config).await? {
+        symlink(&installation, config).await?;
+    }
+    Ok(())
+}
+
 #[cfg(not(windows))]
-async fn symlink(installation: &Installation, config: &Config) -> Result<(), Box<dyn Error>> {
+async fn symlink(installation: &Installation, config: &Config) -> Result<(), Error> {
     let mut versions: VecDeque<(Version, PathBuf)> = cellar::ls(&installation.pkg.project, config)
         .await?
         .into_iter()
@@ -148,7 +285,10 @@ async fn symlink(installation: &Installation, config: &Config) -> Result<(), Box
     versions.make_contiguous().sort_by(|a, b| a.0.cmp(&b.0));
 
     if versions.is_empty() {
-        return Err(format!("no versions for package {}", installation.pkg.project).into());
+        return Err(Error::Other(format!(
+            "no versions for package {}",
+            installation.pkg.project
+        )));
     }
 
     let shelf = installation.path.parent().unwrap();
@@ -171,10 +311,10 @@ async fn symlink(installation: &Installation, config: &Config) -> Result<(), Box
         .iter()
         .rfind(|(version, _)| minor_range.satisfies(version))
         .ok_or_else(|| {
-            anyhow::anyhow!(
+            Error::Other(format!(
                 "Could not find most minor version for {}",
                 installation.pkg.project
-            )
+            ))
         })?;
 
     if most_minor.0 != installation.pkg.version {
@@ -189,7 +329,7 @@ async fn symlink(installation: &Installation, config: &Config) -> Result<(), Box
     let most_major = versions
         .iter()
         .rfind(|(version, _)| major_range.satisfies(version))
-        .ok_or_else(|| anyhow::anyhow!("Could not find most major version"))?;
+        .ok_or_else(|| Error::Other("Could not find most major version".into()))?;
 
     if most_major.0 != installation.pkg.version {
         return Ok(());
@@ -214,7 +354,7 @@ async fn make_symlink(
     shelf: &Path,
     symname: &str,
     installation: &Installation,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<(), Error> {
     let symlink_path = shelf.join(symname);
 
     if symlink_path.is_symlink() {
@@ -225,10 +365,9 @@ async fn make_symlink(
         }
     }
 
-    let target = installation
-        .path
-        .file_name()
-        .ok_or_else(|| anyhow::anyhow!("Could not get the base name of the installation path"))?;
+    let target = installation.path.file_name().ok_or_else(|| {
+        Error::Other("Could not get the base name of the installation path".into())
+    })?;
 
     #[cfg(not(windows))]
     std::os::unix::fs::symlink(target, &symlink_path)?;
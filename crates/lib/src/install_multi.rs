@@ -1,12 +1,13 @@
-use std::error::Error;
 use std::sync::Arc;
 
-use crate::install::{install, InstallEvent};
+use crate::install::{install, install_bottle, Bottle, InstallEvent};
+use crate::lockfile::LockedPackage;
 use crate::types::{Installation, Package};
-use futures::stream::FuturesUnordered;
+use futures::stream;
 use futures::StreamExt;
 
 use crate::config::Config;
+use crate::error::Error;
 
 pub trait ProgressBarExt {
     fn inc(&self, n: u64);
@@ -17,28 +18,46 @@ pub async fn install_multi(
     pending: &[Package],
     config: &Config,
     pb: Option<Arc<impl ProgressBarExt + Send + Sync + 'static>>,
-) -> Result<Vec<Installation>, Box<dyn Error>> {
-    pending
+) -> Result<Vec<Installation>, Error> {
+    stream::iter(pending)
+        .map(|pkg| install(pkg, config, progress(&pb)))
+        .buffer_unordered(config.download_concurrency)
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect()
+}
+
+/// Installs exactly the bottles pinned by a lockfile.
+pub async fn install_multi_locked(
+    pending: &[LockedPackage],
+    config: &Config,
+    pb: Option<Arc<impl ProgressBarExt + Send + Sync + 'static>>,
+) -> Result<Vec<Installation>, Error> {
+    let pending: Vec<(Package, Bottle)> = pending
         .iter()
-        .map(|pkg| {
-            install(
-                pkg,
-                config,
-                pb.clone().map(|pb| {
-                    move |event| match event {
-                        InstallEvent::DownloadSize(size) => {
-                            pb.inc_length(size);
-                        }
-                        InstallEvent::Progress(chunk) => {
-                            pb.inc(chunk);
-                        }
-                    }
-                }),
-            )
-        })
-        .collect::<FuturesUnordered<_>>()
+        .map(|locked| (locked.pkg(), locked.bottle()))
+        .collect();
+    stream::iter(&pending)
+        .map(|(pkg, bottle)| install_bottle(pkg, Some(bottle), config, progress(&pb)))
+        .buffer_unordered(config.download_concurrency)
         .collect::<Vec<_>>()
         .await
         .into_iter()
         .collect()
 }
+
+fn progress(
+    pb: &Option<Arc<impl ProgressBarExt + Send + Sync + 'static>>,
+) -> Option<impl FnMut(InstallEvent) + Send + 'static> {
+    pb.clone().map(|pb| {
+        move |event| match event {
+            InstallEvent::DownloadSize(size) => {
+                pb.inc_length(size);
+            }
+            InstallEvent::Progress(chunk) => {
+                pb.inc(chunk);
+            }
+        }
+    })
+}
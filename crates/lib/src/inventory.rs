@@ -1,12 +1,19 @@
 use crate::client::build_client;
 use crate::config::Config;
-use crate::types::{host, Package, PackageReq};
+use crate::download;
+use crate::error::Error;
+use crate::types::{Package, PackageReq, Platform};
+use crate::VersionRange;
 use libsemverator::semver::Semver as Version;
-use reqwest::Url;
-use std::error::Error;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 // Select function to pick a version
-pub async fn select(rq: &PackageReq, config: &Config) -> Result<Option<Version>, Box<dyn Error>> {
+pub async fn select(rq: &PackageReq, config: &Config) -> Result<Option<Version>, Error> {
     let versions = ls(&rq.project, config).await?;
 
     Ok(versions
@@ -17,22 +24,9 @@ pub async fn select(rq: &PackageReq, config: &Config) -> Result<Option<Version>,
 }
 
 // Get function to fetch available versions
-pub async fn ls(project: &String, config: &Config) -> Result<Vec<Version>, Box<dyn Error>> {
-    let base_url = config.dist_url.clone();
+pub async fn ls(project: &String, config: &Config) -> Result<Vec<Version>, Error> {
+    let releases = fetch(project, config).await?;
 
-    let (platform, arch) = host();
-    let url = Url::parse(&format!(
-        "{}/{}/{}/{}/versions.txt",
-        base_url, project, platform, arch
-    ))?;
-
-    let rsp = build_client()?
-        .get(url.clone())
-        .send()
-        .await?
-        .error_for_status()?;
-
-    let releases = rsp.text().await?;
     let mut versions: Vec<Version> = releases
         .lines()
         .map(Version::parse)
@@ -40,10 +34,10 @@ pub async fn ls(project: &String, config: &Config) -> Result<Vec<Version>, Box<d
         .collect();
 
     if versions.is_empty() {
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            format!("No inventory for {}", project),
-        )));
+        return Err(Error::NotFound(PackageReq {
+            project: project.clone(),
+            constraint: VersionRange::parse("*")?,
+        }));
     }
 
     if project == "openssl.org" {
@@ -55,11 +49,163 @@ pub async fn ls(project: &String, config: &Config) -> Result<Vec<Version>, Box<d
     Ok(versions)
 }
 
+async fn fetch(project: &String, config: &Config) -> Result<String, Error> {
+    let cache_file = cache_file(project, config);
+    let meta_file = cache_file.with_extension("txt.meta");
+    let cached = read_cache(&cache_file, &meta_file);
+
+    if config.offline {
+        return cached
+            .map(|(releases, _)| releases)
+            .ok_or_else(|| Error::Offline(vec![format!("inventory for {}", project)]));
+    }
+
+    if let Some((releases, meta)) = &cached {
+        if now().saturating_sub(meta.fetched_at) < config.inventory_ttl.as_secs() {
+            return Ok(releases.clone());
+        }
+    }
+
+    let path = format!("{}/{}/versions.txt", project, config.platform);
+
+    let client = build_client()?;
+    let rsp = download::get_mirrored(&config.dist_urls, &path, |url| {
+        let mut rq = client.get(url);
+        if let Some((_, meta)) = &cached {
+            // another mirror’s validators don’t describe what this one has
+            let issued = meta.mirror.as_ref();
+            if issued.is_none_or(|mirror| url != format!("{}/{}", mirror, path)) {
+                return rq;
+            }
+            if let Some(etag) = &meta.etag {
+                rq = rq.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &meta.last_modified {
+                rq = rq.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        rq
+    })
+    .await;
+    let rsp = match rsp {
+        // no bottles for this platform (or at all)
+        Err(Error::Network(err)) if err.status() == Some(StatusCode::NOT_FOUND) => {
+            return Err(Error::NotFound(PackageReq {
+                project: project.clone(),
+                constraint: VersionRange::parse("*")?,
+            }))
+        }
+        rsp => rsp?,
+    };
+    let (rsp, mirror) = rsp;
+
+    if rsp.status() == StatusCode::NOT_MODIFIED {
+        if let Some((releases, mut meta)) = cached {
+            meta.fetched_at = now();
+            write_cache(&cache_file, &meta_file, None, &meta);
+            return Ok(releases);
+        }
+    }
+
+    let header = |name| {
+        rsp.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string)
+    };
+    let meta = CacheMeta {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+        mirror: Some(mirror),
+        fetched_at: now(),
+    };
+    let releases = rsp.text().await?;
+    write_cache(&cache_file, &meta_file, Some(&releases), &meta);
+
+    Ok(releases)
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheMeta {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// the dist mirror that served it and so issued the above
+    #[serde(default)]
+    mirror: Option<String>,
+    /// seconds since the epoch that we last fetched or revalidated
+    fetched_at: u64,
+}
+
+// cached next to the pantry db, eg. ~/.cache/pkgx/inventory/nodejs.org/linux/x86-64/versions.txt
+fn cache_file(project: &str, config: &Config) -> PathBuf {
+    config
+        .pantry_db_file
+        .with_file_name("inventory")
+        .join(project)
+        .join(config.platform.host.to_string())
+        .join(config.platform.arch.to_string())
+        .join("versions.txt")
+}
+
+fn read_cache(cache_file: &Path, meta_file: &Path) -> Option<(String, CacheMeta)> {
+    let releases = fs::read_to_string(cache_file).ok()?;
+    let meta = fs::read_to_string(meta_file)
+        .ok()
+        .and_then(|meta| serde_json::from_str(&meta).ok())
+        .unwrap_or(CacheMeta {
+            etag: None,
+            last_modified: None,
+            mirror: None,
+            fetched_at: 0,
+        });
+    Some((releases, meta))
+}
+
+// the cache is an optimization, failing to write it is not fatal. The
+// releases are written first so a reader never pairs new validators with old
+// releases, which would have them revalidated as current.
+fn write_cache(cache_file: &Path, meta_file: &Path, releases: Option<&String>, meta: &CacheMeta) {
+    let _ = (|| -> Result<(), Error> {
+        if let Some(releases) = releases {
+            fs::create_dir_all(cache_file.parent().unwrap())?;
+            write_atomically(cache_file, releases)?;
+        }
+        write_atomically(meta_file, &serde_json::to_string(meta)?)?;
+        Ok(())
+    })();
+}
+
+/// Written alongside and renamed over `path` so concurrent readers see either
+/// the old or the new contents, never half of them.
+fn write_atomically(path: &Path, contents: &str) -> Result<(), Error> {
+    let tmp = path.with_file_name(format!(
+        "{}.{}.tmp",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        std::process::id()
+    ));
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })?;
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
 //TODO xz bottles are preferred
+/// Where `pkg`’s bottle for `platform` is relative to a dist mirror.
+pub fn bottle_path(pkg: &Package, platform: &Platform) -> String {
+    format!("{}/{}/v{}.tar.xz", pkg.project, platform, pkg.version.raw)
+}
+
+/// The URL of `pkg`’s bottle on the first dist mirror, installs fall back to
+/// the others, see `bottle_path`.
 pub fn get_url(pkg: &Package, config: &Config) -> String {
-    let (platform, arch) = host();
-    format!(
-        "{}/{}/{}/{}/v{}.tar.xz",
-        config.dist_url, pkg.project, platform, arch, pkg.version.raw
-    )
+    let mirror = config.dist_urls.first().map(String::as_str).unwrap_or("");
+    format!("{}/{}", mirror, bottle_path(pkg, &config.platform))
 }
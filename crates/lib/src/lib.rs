@@ -1,18 +1,27 @@
 mod cellar;
 mod client;
 pub mod config;
+mod download;
 pub mod env;
+pub mod error;
+pub mod gc;
 pub mod hydrate;
-mod install;
+pub mod install;
 pub mod install_multi;
 pub mod inventory;
-mod pantry;
+pub mod lockfile;
+pub mod moustache;
+pub mod pantry;
 pub mod pantry_db;
 pub mod platform_case_aware_env_key;
 pub mod resolve;
 pub mod sync;
+#[cfg(test)]
+mod tests;
 pub mod types;
 pub mod utils;
 
+pub use error::{Error, Result};
+
 pub type Version = libsemverator::semver::Semver;
 pub type VersionRange = libsemverator::range::Range;
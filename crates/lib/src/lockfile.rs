@@ -0,0 +1,208 @@
+use std::{fs, path::Path};
+
+use futures::future::try_join_all;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cellar,
+    client::build_client,
+    config::Config,
+    error::Error,
+    install::{fetch_mirrored_checksum, Bottle, Receipt},
+    types::{Installation, Package, PackageReq, Platform},
+    Version, VersionRange,
+};
+
+pub const FILENAME: &str = "pkgx.lock";
+
+const FORMAT_VERSION: u32 = 1;
+
+/// A fully resolved package graph pinned to exact bottles.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Lockfile {
+    pub version: u32,
+    /// bottles are per platform so a lockfile is too, eg. `linux/x86-64`
+    pub platform: String,
+    pub pkgs: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockedPackage {
+    pub project: String,
+    pub version: Version,
+    pub url: String,
+    pub sha256: String,
+}
+
+impl LockedPackage {
+    pub fn pkg(&self) -> Package {
+        Package {
+            project: self.project.clone(),
+            version: self.version.clone(),
+        }
+    }
+
+    pub fn bottle(&self) -> Bottle {
+        Bottle {
+            url: self.url.clone(),
+            sha256: self.sha256.clone(),
+        }
+    }
+}
+
+impl Lockfile {
+    /// Pins `pkgs` to their bottles, fetching each published checksum.
+    pub async fn generate(pkgs: &[Package], config: &Config) -> Result<Self, Error> {
+        if config.offline && !pkgs.is_empty() {
+            return Err(Error::Offline(
+                pkgs.iter()
+                    .map(|pkg| format!("checksum for {}", pkg))
+                    .collect(),
+            ));
+        }
+
+        let client = build_client()?;
+        let mut pkgs = try_join_all(pkgs.iter().map(|pkg| {
+            let client = &client;
+            async move {
+                let (url, sha256) = fetch_mirrored_checksum(pkg, config, client).await?;
+                Ok::<_, Error>(LockedPackage {
+                    project: pkg.project.clone(),
+                    version: pkg.version.clone(),
+                    url,
+                    sha256,
+                })
+            }
+        }))
+        .await?;
+
+        // stable output so lockfiles diff cleanly
+        pkgs.sort_by(|a, b| a.project.cmp(&b.project));
+
+        Ok(Self {
+            version: FORMAT_VERSION,
+            platform: config.platform.to_string(),
+            pkgs,
+        })
+    }
+
+    /// Pins `installations` to the bottles their receipts say they were
+    /// installed (and verified) from. Only those without one (eg. installed
+    /// by an older pkgx) have their published checksums fetched.
+    pub async fn from_installations(
+        installations: &[Installation],
+        config: &Config,
+    ) -> Result<Self, Error> {
+        let mut receipted = vec![];
+        let mut unverified = vec![];
+        for installation in installations {
+            match Receipt::read(installation) {
+                Some(receipt) => receipted.push(LockedPackage {
+                    project: installation.pkg.project.clone(),
+                    version: installation.pkg.version.clone(),
+                    url: receipt.url,
+                    sha256: receipt.sha256,
+                }),
+                None => unverified.push(installation.pkg.clone()),
+            }
+        }
+        let mut rv = Self::generate(&unverified, config).await?;
+        rv.pkgs.extend(receipted);
+        rv.pkgs.sort_by(|a, b| a.project.cmp(&b.project));
+        Ok(rv)
+    }
+
+    /// Errors if it isn’t for `platform`.
+    pub fn read(path: &Path, platform: &Platform) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| Error::Lockfile(format!("couldn’t read {}: {}", path.display(), e)))?;
+        let lockfile: Self = serde_json::from_str(&content)
+            .map_err(|e| Error::Lockfile(format!("invalid lockfile {}: {}", path.display(), e)))?;
+
+        if lockfile.version > FORMAT_VERSION {
+            return Err(Error::Lockfile(format!(
+                "{} is format v{}, upgrade pkgx to use it",
+                path.display(),
+                lockfile.version
+            )));
+        }
+        if lockfile.platform != platform.to_string() {
+            return Err(Error::Lockfile(format!(
+                "{} was generated for {} but this is {}",
+                path.display(),
+                lockfile.platform,
+                platform
+            )));
+        }
+
+        Ok(lockfile)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        fs::write(path, format!("{}\n", serde_json::to_string_pretty(self)?))?;
+        Ok(())
+    }
+
+    pub fn get(&self, project: &str) -> Option<&LockedPackage> {
+        self.pkgs.iter().find(|locked| locked.project == project)
+    }
+
+    /// Just what `projects` need of it, following the dependencies `get_deps`
+    /// gives. Errors if any of those isn’t pinned.
+    pub fn subset<F>(&self, projects: &[String], get_deps: F) -> Result<Self, Error>
+    where
+        F: Fn(&str) -> Result<Vec<PackageReq>, Error>,
+    {
+        let mut pkgs: Vec<LockedPackage> = vec![];
+        let mut stack = projects.to_vec();
+        while let Some(project) = stack.pop() {
+            if pkgs.iter().any(|locked| locked.project == project) {
+                continue;
+            }
+            let locked = self.get(&project).ok_or_else(|| {
+                Error::Lockfile(format!(
+                    "{} is not in {}, regenerate it with --lock",
+                    project, FILENAME
+                ))
+            })?;
+            stack.extend(get_deps(&project)?.into_iter().map(|dep| dep.project));
+            pkgs.push(locked.clone());
+        }
+        pkgs.sort_by(|a, b| a.project.cmp(&b.project));
+
+        Ok(Self {
+            version: self.version,
+            platform: self.platform.clone(),
+            pkgs,
+        })
+    }
+
+    /// The pinned packages as exact requirements.
+    pub fn reqs(&self) -> Result<Vec<PackageReq>, Error> {
+        self.pkgs
+            .iter()
+            .map(|locked| {
+                Ok(PackageReq {
+                    project: locked.project.clone(),
+                    constraint: VersionRange::from_semver(&locked.version)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Splits the pinned packages into those already in the cellar and those pending.
+    pub fn partition(&self, config: &Config) -> (Vec<Installation>, Vec<LockedPackage>) {
+        let mut installed = vec![];
+        let mut pending = vec![];
+        for locked in &self.pkgs {
+            let pkg = locked.pkg();
+            let path = cellar::dst(&pkg, config);
+            if path.is_dir() {
+                installed.push(Installation { path, pkg });
+            } else {
+                pending.push(locked.clone());
+            }
+        }
+        (installed, pending)
+    }
+}
@@ -0,0 +1,89 @@
+use crate::{config::Config, error::Error, types::Installation, Version};
+
+/// Renders the `{{…}}`s in a pantry value for `pkg`, eg. `{{prefix}}/bin`.
+///
+/// `prefix`, `version` and `version.major`, `.minor`, `.patch`, `.marketing`,
+/// `.build` and `.raw` are `pkg`’s, `deps.X.` in front of any of those is the
+/// dependency `X`’s. `hw.platform`, `hw.arch`, `home` and `pkgx.prefix` are
+/// ours. Anything else is an error rather than being left in the value.
+pub fn render(
+    template: &str,
+    pkg: &Installation,
+    deps: &[Installation],
+    config: &Config,
+) -> Result<String, Error> {
+    let mut rv = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| end + start) else {
+            return Err(Error::Parse(format!(
+                "{}: unterminated moustache in `{}`",
+                pkg.pkg.project, template
+            )));
+        };
+        // `${{prefix}}` is seen in the wild and means the same thing
+        rv.push_str(rest[..start].strip_suffix('$').unwrap_or(&rest[..start]));
+        let key = rest[start + 2..end].trim();
+        match value(key, pkg, deps, config)? {
+            Some(value) => rv.push_str(&value),
+            None => {
+                return Err(Error::Parse(format!(
+                    "{}: unknown moustache `{{{{{}}}}}`",
+                    pkg.pkg.project, key
+                )))
+            }
+        }
+        rest = &rest[end + 2..];
+    }
+    rv.push_str(rest);
+    Ok(rv)
+}
+
+fn value(
+    key: &str,
+    pkg: &Installation,
+    deps: &[Installation],
+    config: &Config,
+) -> Result<Option<String>, Error> {
+    let rv = match key {
+        "hw.platform" => config.platform.host.to_string(),
+        "hw.arch" => config.platform.arch.to_string(),
+        "pkgx.prefix" => config.pkgx_dir.to_string_lossy().to_string(),
+        "home" => dirs_next::home_dir()
+            .ok_or_else(|| Error::Other("couldn’t determine the home directory".to_string()))?
+            .to_string_lossy()
+            .to_string(),
+        key => {
+            if let Some(key) = key.strip_prefix("deps.") {
+                // projects have dots in them so we can’t just split
+                return Ok(deps.iter().find_map(|dep| {
+                    let key = key.strip_prefix(&dep.pkg.project)?.strip_prefix('.')?;
+                    pkg_value(key, dep)
+                }));
+            }
+            return Ok(pkg_value(key, pkg));
+        }
+    };
+    Ok(Some(rv))
+}
+
+fn pkg_value(key: &str, installation: &Installation) -> Option<String> {
+    match key {
+        "prefix" => Some(installation.path.to_string_lossy().to_string()),
+        key => version_value(key.strip_prefix("version")?, &installation.pkg.version),
+    }
+}
+
+fn version_value(key: &str, version: &Version) -> Option<String> {
+    let rv = match key {
+        "" => version.to_string(),
+        ".major" => version.major.to_string(),
+        ".minor" => version.minor.to_string(),
+        ".patch" => version.patch.to_string(),
+        ".marketing" => format!("{}.{}", version.major, version.minor),
+        ".build" => version.build.join("."),
+        ".raw" => version.raw.clone(),
+        _ => return None,
+    };
+    Some(rv)
+}
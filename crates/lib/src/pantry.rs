@@ -1,9 +1,16 @@
-use crate::{config::Config, types::PackageReq};
+use crate::{
+    config::Config,
+    error::Error,
+    types::{PackageReq, Platform},
+};
 use libsemverator::range::Range as VersionReq;
-use serde::Deserialize;
-use serde::Deserializer;
-use std::collections::HashMap;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
+use std::marker::PhantomData;
 use std::path::PathBuf;
 
 pub struct PantryEntry {
@@ -12,100 +19,93 @@ pub struct PantryEntry {
     pub programs: Vec<String>,
     pub companions: Vec<PackageReq>,
     pub env: HashMap<String, String>,
+    /// directories for path-like variables, see `env::Paths`
+    pub paths: HashMap<String, Vec<String>>,
     pub display_name: Option<String>,
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub license: Option<String>,
+    /// eg. `darwin` or `linux/x86-64`, empty if it builds everywhere
+    pub platforms: Vec<String>,
+    /// only needed to build it, not to run it
+    pub build_deps: Vec<PackageReq>,
 }
 
 impl PantryEntry {
-    fn from_path(path: &PathBuf, pantry_dir: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
-        let project = path
-            .parent()
-            .unwrap()
-            .strip_prefix(pantry_dir)
-            .unwrap()
-            .to_str()
-            .unwrap()
-            .to_string();
-
-        #[cfg(windows)]
-        let project = project.replace("\\", "/");
-
-        Self::from_raw_entry(RawPantryEntry::from_path(path)?, project)
+    /// What `file` says for `platform`.
+    pub fn from_file(file: &PackageFile, platform: &Platform) -> Result<Self, Error> {
+        Ok(Self::from_raw_entry(
+            RawPantryEntry::from_path(&file.path)?,
+            file.project.clone(),
+            platform,
+        ))
     }
 
-    fn from_raw_entry(
-        entry: RawPantryEntry,
-        project: String,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
-        let deps = if let Some(deps) = entry.dependencies {
-            deps.0
-                .iter()
-                .map(|(project, constraint)| {
-                    VersionReq::parse(constraint).map(|constraint| PackageReq {
-                        project: project.clone(),
-                        constraint,
-                    })
-                })
-                .collect::<Result<Vec<_>, _>>()?
-        } else {
-            vec![]
-        };
+    fn from_raw_entry(entry: RawPantryEntry, project: String, platform: &Platform) -> Self {
+        let reqs = |deps: Option<Deps>| deps.map(|deps| deps.reqs(platform)).unwrap_or_default();
+
+        let deps = reqs(entry.dependencies);
 
         let programs = if let Some(provides) = entry.provides {
-            provides.0
+            provides.programs(platform)
         } else {
             vec![]
         };
 
-        let companions = if let Some(companions) = entry.companions {
-            companions
-                .0
-                .iter()
-                .map(|(k, v)| {
-                    VersionReq::parse(v).map(|constraint| PackageReq {
-                        project: k.clone(),
-                        constraint,
-                    })
-                })
-                .collect::<Result<Vec<_>, _>>()?
-        } else {
-            vec![]
-        };
+        let companions = reqs(entry.companions);
 
-        let env = if let Some(runtime) = entry.runtime {
-            runtime.env
+        let (env, paths) = if let Some(runtime) = entry.runtime {
+            (runtime.env.values(platform), runtime.paths)
         } else {
-            HashMap::new()
+            (HashMap::new(), HashMap::new())
         };
 
-        let display_name = entry.display_name;
+        let build_deps = reqs(entry.build.and_then(|build| build.dependencies));
 
-        Ok(Self {
+        Self {
             deps,
             project,
             env,
+            paths,
             companions,
             programs,
-            display_name,
-        })
+            display_name: entry.display_name,
+            description: entry.description,
+            homepage: entry.homepage,
+            license: entry.license.map(|license| license.0.join(" OR ")),
+            platforms: entry
+                .platforms
+                .map(|platforms| platforms.0)
+                .unwrap_or_default(),
+            build_deps,
+        }
     }
 }
 
-pub struct PackageEntryIterator {
+/// A `package.yml` and the project it describes.
+pub struct PackageFile {
+    pub project: String,
+    pub path: PathBuf,
+    /// the pantry it is from
+    pub pantry: PathBuf,
+}
+
+pub struct PackageFileIterator {
     stack: Vec<PathBuf>, // stack for directories to visit
     pantry_dir: PathBuf,
 }
 
-impl PackageEntryIterator {
+impl PackageFileIterator {
     pub fn new(pantry_dir: PathBuf) -> Self {
         Self {
-            stack: vec![pantry_dir.clone()],
+            stack: vec![pantry_dir.join("projects")],
             pantry_dir,
         }
     }
 }
 
-impl Iterator for PackageEntryIterator {
-    type Item = PantryEntry;
+impl Iterator for PackageFileIterator {
+    type Item = PackageFile;
 
     fn next(&mut self) -> Option<Self::Item> {
         while let Some(path) = self.stack.pop() {
@@ -117,194 +117,645 @@ impl Iterator for PackageEntryIterator {
                     }
                 }
             } else if path.file_name() == Some("package.yml".as_ref()) {
-                if let Ok(entry) = PantryEntry::from_path(&path, &self.pantry_dir) {
-                    return Some(entry);
-                } else if cfg!(debug_assertions) {
-                    eprintln!("parse failure: {:?}", path);
-                }
+                let project = path
+                    .parent()
+                    .unwrap()
+                    .strip_prefix(self.pantry_dir.join("projects"))
+                    .unwrap()
+                    .to_str()
+                    .unwrap()
+                    .to_string();
+
+                #[cfg(windows)]
+                let project = project.replace("\\", "/");
+
+                return Some(PackageFile {
+                    project,
+                    path,
+                    pantry: self.pantry_dir.clone(),
+                });
             }
         }
         None
     }
 }
 
-pub fn ls(config: &Config) -> PackageEntryIterator {
-    PackageEntryIterator::new(config.pantry_dir.join("projects"))
+/// Every `package.yml` across the pantries, where a project is in more than
+/// one only the one from the pantry that takes precedence is included.
+pub fn files(config: &Config) -> impl Iterator<Item = PackageFile> + '_ {
+    let mut seen = HashSet::new();
+    config
+        .pantries()
+        .into_iter()
+        .flat_map(|pantry| PackageFileIterator::new(pantry.to_path_buf()))
+        .filter(move |file| seen.insert(file.project.clone()))
+}
+
+/// A `package.yml` we couldn’t make sense of.
+#[derive(Debug, Serialize)]
+pub struct Issue {
+    pub path: PathBuf,
+    /// where in the file, if the problem is with a specific part of it
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub reason: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, ":{}:{}", line, column)?;
+        }
+        write!(f, ": {}", self.reason)
+    }
+}
+
+/// Checks every `package.yml` in the pantry, reporting each entry that the
+/// pantry db skips, or why it left the whole file out. Every platform’s
+/// section is checked, not just ours.
+pub fn lint(config: &Config) -> Vec<Issue> {
+    let mut issues: Vec<Issue> = files(config).flat_map(|file| check(&file)).collect();
+    issues.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    issues
+}
+
+fn check(file: &PackageFile) -> Vec<Issue> {
+    let issue = |line, column, reason| Issue {
+        path: file.path.clone(),
+        line,
+        column,
+        reason,
+    };
+    let content = match fs::read_to_string(&file.path) {
+        Ok(content) => content,
+        Err(err) => return vec![issue(None, None, err.to_string())],
+    };
+    let mut issues = vec![];
+    let mut skip: Vec<usize> = vec![];
+    loop {
+        LINT.set(Some(Lint {
+            skip: skip.clone(),
+            ..Lint::default()
+        }));
+        let entry = serde_yaml::from_str::<RawPantryEntry>(&content);
+        let lint = LINT.take().unwrap_or_default();
+        let Err(err) = entry else {
+            return issues;
+        };
+        let new = match err.location() {
+            Some(location) => {
+                // the location is reported separately
+                let at = format!(" at line {} column {}", location.line(), location.column());
+                let reason = err.to_string().replacen(&at, "", 1);
+                issue(Some(location.line()), Some(location.column()), reason)
+            }
+            None => issue(None, None, err.to_string()),
+        };
+        // skipping what a syntax error is in just fails on it again
+        if issues.last().is_none_or(|last: &Issue| {
+            (last.line, last.column, &last.reason) != (new.line, new.column, &new.reason)
+        }) {
+            issues.push(new);
+        }
+        match lint.failed {
+            Some(n) if skip.last().is_none_or(|last| n > *last) => skip.push(n),
+            // not something the pantry db skips so it doesn’t parse at all
+            _ => return issues,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 struct RawPantryEntry {
+    #[serde(default, deserialize_with = "lenient")]
     dependencies: Option<Deps>,
+    #[serde(default, deserialize_with = "lenient")]
     provides: Option<Provides>,
+    #[serde(default, deserialize_with = "lenient")]
     companions: Option<Deps>,
+    #[serde(default, deserialize_with = "lenient")]
     runtime: Option<Runtime>,
-    #[serde(rename = "display-name")]
+    #[serde(rename = "display-name", default, deserialize_with = "lenient")]
     display_name: Option<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    description: Option<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    homepage: Option<String>,
+    #[serde(default, deserialize_with = "lenient")]
+    license: Option<OneOrMany>,
+    #[serde(default, deserialize_with = "lenient")]
+    platforms: Option<OneOrMany>,
+    #[serde(default, deserialize_with = "lenient")]
+    build: Option<Build>,
+}
+
+/// A string or a list of them, eg. `platforms: darwin`
+#[derive(Debug, Deserialize)]
+#[serde(from = "OneOrManyHelper")]
+pub(crate) struct OneOrMany(pub Vec<String>);
+
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "a string or a list of them")]
+enum OneOrManyHelper {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<OneOrManyHelper> for OneOrMany {
+    fn from(helper: OneOrManyHelper) -> Self {
+        match helper {
+            OneOrManyHelper::One(one) => OneOrMany(vec![one]),
+            OneOrManyHelper::Many(many) => OneOrMany(many),
+        }
+    }
 }
 
 #[derive(Debug)]
-struct Runtime {
-    env: HashMap<String, String>,
+struct Build {
+    dependencies: Option<Deps>,
 }
 
-impl<'de> Deserialize<'de> for Runtime {
+impl<'de> Deserialize<'de> for Build {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        #[cfg(target_os = "macos")]
-        let platform_key = "darwin";
-        #[cfg(target_os = "linux")]
-        let platform_key = "linux";
-        #[cfg(target_os = "windows")]
-        let platform_key = "windows";
-        #[cfg(target_arch = "aarch64")]
-        let arch_key = "aarch64";
-        #[cfg(target_arch = "x86_64")]
-        let arch_key = "x86-64";
-
-        fn stringify(value: serde_yaml::Value) -> Option<String> {
-            match value {
-                serde_yaml::Value::String(s) => Some(s.clone()),
-                serde_yaml::Value::Number(n) => Some(n.to_string()),
-                serde_yaml::Value::Bool(b) => Some(b.to_string()),
-                _ => None,
+        struct BuildVisitor;
+
+        impl<'de> Visitor<'de> for BuildVisitor {
+            type Value = Build;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a build section")
             }
-        }
 
-        let mut result = HashMap::new();
-
-        let root: HashMap<String, serde_yaml::Value> = Deserialize::deserialize(deserializer)?;
-
-        if let Some(env) = root.get("env").and_then(|x| x.as_mapping()).cloned() {
-            for (key, value) in env {
-                if key == "linux" || key == "darwin" || key == "windows" {
-                    // If the key is platform-specific, only include values for the current platform
-                    if key == platform_key {
-                        if let serde_yaml::Value::Mapping(value) = value {
-                            for (key, value) in value {
-                                if let (Some(key), Some(value)) = (stringify(key), stringify(value))
-                                {
-                                    result.insert(key, value);
-                                }
-                            }
-                        }
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Build, M::Error> {
+                let mut dependencies = None;
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == "dependencies" {
+                        dependencies = next_value(&mut map)?;
+                    } else {
+                        map.next_value::<de::IgnoredAny>()?;
                     }
-                } else if key == "aarch64" || key == "x86-64" {
-                    if key == arch_key {
-                        if let serde_yaml::Value::Mapping(value) = value {
-                            for (key, value) in value {
-                                if let (Some(key), Some(value)) = (stringify(key), stringify(value))
-                                {
-                                    result.insert(key, value);
-                                }
-                            }
-                        }
+                }
+                Ok(Build { dependencies })
+            }
+
+            // a bare build script has no dependencies
+            fn visit_str<E: de::Error>(self, _: &str) -> Result<Build, E> {
+                Ok(Build { dependencies: None })
+            }
+
+            fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Build, S::Error> {
+                while seq.next_element::<de::IgnoredAny>()?.is_some() {}
+                Ok(Build { dependencies: None })
+            }
+        }
+
+        deserializer.deserialize_any(BuildVisitor)
+    }
+}
+
+const PLATFORMS: [&str; 3] = ["linux", "darwin", "windows"];
+const ARCHS: [&str; 2] = ["aarch64", "x86-64"];
+
+thread_local! {
+    // only `lint` fails a package.yml over an entry we don’t understand, the
+    // pantry db skips the entry and keeps the rest of the package
+    static LINT: RefCell<Option<Lint>> = const { RefCell::new(None) };
+}
+
+/// The values the pantry db would skip are numbered in the order they are
+/// parsed. Linting fails on the first not in `skip`, which the next parse
+/// then skips, until the rest of the file parses.
+#[derive(Default)]
+struct Lint {
+    seen: usize,
+    skip: Vec<usize>,
+    /// the value this parse failed on
+    failed: Option<usize>,
+}
+
+enum Attempt {
+    Lenient,
+    Skip,
+    Strict(usize),
+}
+
+fn attempt() -> Attempt {
+    LINT.with_borrow_mut(|lint| {
+        let Some(lint) = lint else {
+            return Attempt::Lenient;
+        };
+        let n = lint.seen;
+        lint.seen += 1;
+        if lint.skip.contains(&n) {
+            Attempt::Skip
+        } else {
+            Attempt::Strict(n)
+        }
+    })
+}
+
+// the innermost value is the one to skip, so the first to fail
+fn failed(n: usize) {
+    LINT.with_borrow_mut(|lint| {
+        if let Some(lint) = lint {
+            lint.failed.get_or_insert(n);
+        }
+    })
+}
+
+/// The next value in `map`, `None` if it isn’t a `T` and we aren’t linting.
+fn next_value<'de, M: MapAccess<'de>, T: DeserializeOwned>(
+    map: &mut M,
+) -> Result<Option<T>, M::Error> {
+    match attempt() {
+        Attempt::Lenient => {
+            let value: serde_yaml::Value = map.next_value()?;
+            Ok(T::deserialize(value).ok())
+        }
+        Attempt::Skip => map.next_value::<de::IgnoredAny>().map(|_| None),
+        Attempt::Strict(n) => map.next_value().map(Some).inspect_err(|_| failed(n)),
+    }
+}
+
+/// For the fields of `RawPantryEntry`, as `next_value`.
+fn lenient<'de, D: Deserializer<'de>, T: DeserializeOwned>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error> {
+    match attempt() {
+        Attempt::Lenient => {
+            let value = serde_yaml::Value::deserialize(deserializer)?;
+            Ok(T::deserialize(value).ok())
+        }
+        Attempt::Skip => de::IgnoredAny::deserialize(deserializer).map(|_| None),
+        Attempt::Strict(n) => Option::deserialize(deserializer).inspect_err(|_| failed(n)),
+    }
+}
+
+/// A map of which only the entries that are a `T` are kept, see `next_value`.
+struct Entries<T>(HashMap<String, T>);
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Entries<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct EntriesVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: DeserializeOwned> Visitor<'de> for EntriesVisitor<T> {
+            type Value = Entries<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Entries<T>, M::Error> {
+                let mut entries = HashMap::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    if let Some(value) = next_value(&mut map)? {
+                        entries.insert(key, value);
                     }
-                } else if let (Some(key), Some(value)) = (stringify(key), stringify(value)) {
-                    result.insert(key, value);
                 }
+                Ok(Entries(entries))
             }
         }
-        Ok(Runtime { env: result })
+
+        deserializer.deserialize_map(EntriesVisitor(PhantomData))
     }
 }
 
 #[derive(Debug)]
-struct Deps(HashMap<String, String>);
+struct Runtime {
+    env: Env,
+    /// eg. `PYTHONPATH: lib/python{{version.marketing}}/site-packages`, as
+    /// directories that don’t exist are skipped these aren’t per platform
+    paths: HashMap<String, Vec<String>>,
+}
 
-impl<'de> Deserialize<'de> for Deps {
+impl<'de> Deserialize<'de> for Runtime {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        // Deserialize the map as a generic HashMap
-        let full_map: HashMap<String, serde_yaml::Value> = Deserialize::deserialize(deserializer)?;
-
-        // Determine the current platform
-        #[cfg(target_os = "macos")]
-        let platform_key = "darwin";
-
-        #[cfg(target_os = "linux")]
-        let platform_key = "linux";
-
-        #[cfg(target_os = "windows")]
-        let platform_key = "windows";
-
-        // Create the result map
-        let mut result = HashMap::new();
-
-        fn handle_value(input: &serde_yaml::Value) -> Option<String> {
-            match input {
-                serde_yaml::Value::String(s) => Some(if s.chars().next().unwrap().is_numeric() {
-                    format!("^{}", s)
-                } else {
-                    s.clone()
-                }),
-                serde_yaml::Value::Number(n) => Some(format!("^{}", n)),
-                _ => None,
+        #[derive(Deserialize)]
+        struct RawRuntime {
+            #[serde(default, deserialize_with = "lenient")]
+            env: Option<Env>,
+            #[serde(default, deserialize_with = "lenient")]
+            paths: Option<Entries<OneOrMany>>,
+        }
+
+        let raw = RawRuntime::deserialize(deserializer)?;
+        Ok(Runtime {
+            env: raw.env.unwrap_or_default(),
+            paths: raw
+                .paths
+                .map(|paths| paths.0)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(key, suffixes)| (key, suffixes.0))
+                .collect(),
+        })
+    }
+}
+
+/// Environment variables, those under a platform or arch key (eg. `darwin:`)
+/// are kept apart until we know which platform we want.
+#[derive(Debug, Default)]
+struct Env {
+    all: HashMap<String, String>,
+    sections: HashMap<String, HashMap<String, String>>,
+}
+
+impl Env {
+    fn values(mut self, platform: &Platform) -> HashMap<String, String> {
+        let mut env = self.all;
+        for key in [platform.host.to_string(), platform.arch.to_string()] {
+            env.extend(self.sections.remove(&key).unwrap_or_default());
+        }
+        env
+    }
+}
+
+impl<'de> Deserialize<'de> for Env {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct EnvVisitor;
+
+        impl<'de> Visitor<'de> for EnvVisitor {
+            type Value = Env;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of environment variables")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Env, M::Error> {
+                let mut env = Env::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    if PLATFORMS.contains(&key.as_str()) || ARCHS.contains(&key.as_str()) {
+                        let Some(values) = next_value::<_, Entries<Scalar>>(&mut map)? else {
+                            continue;
+                        };
+                        let section = env.sections.entry(key).or_default();
+                        section.extend(values.0.into_iter().map(|(k, v)| (k, v.0)));
+                    } else if let Some(value) = next_value::<_, Scalar>(&mut map)? {
+                        env.all.insert(key, value.0);
+                    }
+                }
+                Ok(env)
             }
         }
 
-        for (key, value) in full_map {
-            if key == "linux" || key == "darwin" || key == "windows" {
-                // If the key is platform-specific, only include values for the current platform
-                if key == platform_key {
-                    if let serde_yaml::Value::Mapping(platform_values) = value {
-                        for (k, v) in platform_values {
-                            if let (serde_yaml::Value::String(k), Some(v)) = (k, handle_value(&v)) {
-                                result.insert(k, v);
-                            }
-                        }
+        deserializer.deserialize_map(EnvVisitor)
+    }
+}
+
+/// A string, number or bool, as a string.
+struct Scalar(String);
+
+impl<'de> Deserialize<'de> for Scalar {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ScalarVisitor;
+
+        impl Visitor<'_> for ScalarVisitor {
+            type Value = Scalar;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string, number or bool")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Scalar, E> {
+                Ok(Scalar(s.to_string()))
+            }
+
+            fn visit_bool<E: de::Error>(self, b: bool) -> Result<Scalar, E> {
+                Ok(Scalar(b.to_string()))
+            }
+
+            fn visit_i64<E: de::Error>(self, n: i64) -> Result<Scalar, E> {
+                Ok(Scalar(n.to_string()))
+            }
+
+            fn visit_u64<E: de::Error>(self, n: u64) -> Result<Scalar, E> {
+                Ok(Scalar(n.to_string()))
+            }
+
+            fn visit_f64<E: de::Error>(self, n: f64) -> Result<Scalar, E> {
+                Ok(Scalar(n.to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(ScalarVisitor)
+    }
+}
+
+/// Projects and their constraints, those under a platform key (eg. `linux:`)
+/// are kept apart until we know which platform we want.
+#[derive(Debug, Default)]
+struct Deps {
+    all: HashMap<String, VersionReq>,
+    platforms: HashMap<String, HashMap<String, VersionReq>>,
+}
+
+impl Deps {
+    fn reqs(mut self, platform: &Platform) -> Vec<PackageReq> {
+        let mut deps = self.all;
+        deps.extend(
+            self.platforms
+                .remove(&platform.host.to_string())
+                .unwrap_or_default(),
+        );
+        deps.into_iter()
+            .map(|(project, constraint)| PackageReq {
+                project,
+                constraint,
+            })
+            .collect()
+    }
+}
+
+impl<'de> Deserialize<'de> for Deps {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct DepsVisitor;
+
+        impl<'de> Visitor<'de> for DepsVisitor {
+            type Value = Deps;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a map of projects to version constraints")
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Deps, M::Error> {
+                let mut deps = Deps::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    if PLATFORMS.contains(&key.as_str()) {
+                        let Some(values) = next_value::<_, Entries<Constraint>>(&mut map)? else {
+                            continue;
+                        };
+                        let section = deps.platforms.entry(key).or_default();
+                        section.extend(values.0.into_iter().map(|(k, v)| (k, v.0)));
+                    } else if let Some(constraint) = next_value::<_, Constraint>(&mut map)? {
+                        deps.all.insert(key, constraint.0);
                     }
                 }
-            } else if let Some(value) = handle_value(&value) {
-                result.insert(key, value);
+                Ok(deps)
             }
         }
 
-        Ok(Deps(result))
+        deserializer.deserialize_map(DepsVisitor)
     }
 }
 
+/// A version constraint where a bare version means `^version`.
+struct Constraint(VersionReq);
+
+impl<'de> Deserialize<'de> for Constraint {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ConstraintVisitor;
+
+        impl Visitor<'_> for ConstraintVisitor {
+            type Value = Constraint;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a version constraint")
+            }
+
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Constraint, E> {
+                let constraint = match s.chars().next() {
+                    None => return Err(E::custom("empty version constraint")),
+                    Some(c) if c.is_numeric() => format!("^{}", s),
+                    Some(_) => s.to_string(),
+                };
+                VersionReq::parse(&constraint)
+                    .map(Constraint)
+                    .map_err(|_| E::custom(format!("invalid version constraint `{}`", s)))
+            }
+
+            fn visit_u64<E: de::Error>(self, n: u64) -> Result<Constraint, E> {
+                self.visit_str(&n.to_string())
+            }
+
+            fn visit_i64<E: de::Error>(self, n: i64) -> Result<Constraint, E> {
+                self.visit_str(&n.to_string())
+            }
+
+            fn visit_f64<E: de::Error>(self, n: f64) -> Result<Constraint, E> {
+                self.visit_str(&n.to_string())
+            }
+        }
+
+        deserializer.deserialize_any(ConstraintVisitor)
+    }
+}
+
+/// Either the same programs everywhere or a list per platform.
 #[derive(Debug)]
-struct Provides(Vec<String>);
+enum Provides {
+    All(Vec<String>),
+    Platforms(HashMap<String, Vec<String>>),
+}
+
+impl Provides {
+    fn programs(self, platform: &Platform) -> Vec<String> {
+        match self {
+            Provides::All(programs) => programs,
+            Provides::Platforms(mut platforms) => platforms
+                .remove(&platform.host.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
 
 impl<'de> Deserialize<'de> for Provides {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        // Define an enum to capture the possible YAML structures
-        #[derive(Deserialize)]
-        #[serde(untagged)]
-        enum ProvidesHelper {
-            List(Vec<String>),
-            Map(HashMap<String, Vec<String>>),
-        }
+        struct ProvidesVisitor;
 
-        match ProvidesHelper::deserialize(deserializer)? {
-            ProvidesHelper::List(list) => Ok(Provides(list)),
-            ProvidesHelper::Map(map) => {
-                #[cfg(target_os = "macos")]
-                let key = "darwin";
-                #[cfg(target_os = "linux")]
-                let key = "linux";
-                #[cfg(windows)]
-                let key = "windows";
+        impl<'de> Visitor<'de> for ProvidesVisitor {
+            type Value = Provides;
 
-                if let Some(values) = map.get(key) {
-                    Ok(Provides(values.clone()))
-                } else {
-                    Ok(Provides(Vec::new())) // Return an empty Vec if the key isn't found
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list of programs or a map of platforms to them")
+            }
+
+            fn visit_seq<S: SeqAccess<'de>>(self, mut seq: S) -> Result<Provides, S::Error> {
+                let mut programs = vec![];
+                while let Some(program) = seq.next_element()? {
+                    programs.push(program);
                 }
+                Ok(Provides::All(programs))
+            }
+
+            fn visit_map<M: MapAccess<'de>>(self, mut map: M) -> Result<Provides, M::Error> {
+                let mut platforms = HashMap::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    if !PLATFORMS.contains(&key.as_str()) {
+                        match attempt() {
+                            Attempt::Strict(n) => {
+                                map.next_value_seed(UnknownPlatform(&key))
+                                    .inspect_err(|_| failed(n))?;
+                            }
+                            _ => {
+                                map.next_value::<de::IgnoredAny>()?;
+                            }
+                        }
+                    } else if let Some(programs) = next_value(&mut map)? {
+                        platforms.insert(key, programs);
+                    }
+                }
+                Ok(Provides::Platforms(platforms))
             }
         }
+
+        deserializer.deserialize_any(ProvidesVisitor)
+    }
+}
+
+// fails on the value rather than the key so the error is reported with it
+struct UnknownPlatform<'a>(&'a str);
+
+impl<'de> DeserializeSeed<'de> for UnknownPlatform<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for UnknownPlatform<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "one of `{}`", PLATFORMS.join("`, `"))
+    }
+
+    fn visit_seq<S: SeqAccess<'de>>(self, _: S) -> Result<(), S::Error> {
+        Err(de::Error::unknown_variant(self.0, &PLATFORMS))
+    }
+
+    fn visit_str<E: de::Error>(self, _: &str) -> Result<(), E> {
+        Err(de::Error::unknown_variant(self.0, &PLATFORMS))
     }
 }
 
 impl RawPantryEntry {
-    fn from_path(path: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
+    fn from_path(path: &PathBuf) -> Result<Self, Error> {
         let content = fs::read_to_string(path)?;
         Ok(serde_yaml::from_str(&content)?)
     }
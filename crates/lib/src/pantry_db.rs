@@ -1,20 +1,30 @@
-use std::{collections::HashMap, error::Error};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
 
-use crate::{config::Config, pantry, types::PackageReq};
+use crate::{
+    config::Config,
+    error::Error,
+    pantry::{self, PantryEntry},
+    types::{PackageReq, Platform},
+    utils::edit_distance,
+};
 
-pub fn cache(config: &Config, conn: &mut Connection) -> Result<(), Box<dyn Error>> {
-    conn.execute_batch(
-        "
-    PRAGMA synchronous = OFF;
-    PRAGMA journal_mode = MEMORY;
-    PRAGMA temp_store = MEMORY;
-    DROP TABLE IF EXISTS provides;
-    DROP TABLE IF EXISTS dependencies;
-    DROP TABLE IF EXISTS companions;
-    DROP TABLE IF EXISTS runtime_env;
-    DROP TABLE IF EXISTS aliases;
+pub const FILENAME: &str = "pantry.2.db";
+
+/// Each takes the schema from the version of its index to the next, so never
+/// edit one, append another. If it changes what is parsed from `package.yml`
+/// end it with `DELETE FROM projects;` to force a rebuild.
+const MIGRATIONS: &[&str] = &[
+    // 1: the schema when we started versioning it, a db older than this is rebuilt
+    "
     CREATE TABLE provides (
         project TEXT,
         program TEXT
@@ -35,78 +45,525 @@ pub fn cache(config: &Config, conn: &mut Connection) -> Result<(), Box<dyn Error
         project TEXT,
         alias TEXT
     );
+    CREATE TABLE projects (
+        project TEXT PRIMARY KEY,
+        hash TEXT
+    );
+    CREATE TABLE meta (
+        key TEXT PRIMARY KEY,
+        value TEXT
+    );
     CREATE INDEX idx_project ON provides(project);
     CREATE INDEX idx_program ON provides(program);
     CREATE INDEX idx_project_dependencies ON dependencies(project);
     CREATE INDEX idx_project_companions ON companions(project);
     CREATE INDEX idx_alias_project ON aliases(alias);
     ",
-    )?;
+    // 2: which pantry each project is from
+    "
+    ALTER TABLE projects ADD COLUMN pantry TEXT;
+    DELETE FROM projects;
+    ",
+    // 3: what `--info` shows
+    "
+    CREATE TABLE metadata (
+        project TEXT PRIMARY KEY,
+        description TEXT,
+        homepage TEXT,
+        license TEXT
+    );
+    CREATE TABLE platforms (
+        project TEXT,
+        platform TEXT
+    );
+    CREATE TABLE build_dependencies (
+        project TEXT,
+        pkgspec TEXT
+    );
+    CREATE INDEX idx_project_platforms ON platforms(project);
+    CREATE INDEX idx_project_build_dependencies ON build_dependencies(project);
+    DELETE FROM projects;
+    ",
+    // 4: full-text search
+    "
+    CREATE VIRTUAL TABLE search USING fts5(
+        project,
+        programs,
+        names,
+        description
+    );
+    DELETE FROM projects;
+    ",
+    // 5: directories packages add to path-like variables
+    "
+    CREATE TABLE runtime_paths (
+        project TEXT,
+        key TEXT,
+        suffix TEXT
+    );
+    CREATE INDEX idx_project_runtime_paths ON runtime_paths(project);
+    DELETE FROM projects;
+    ",
+];
 
-    let tx = conn.transaction()?;
+pub const SCHEMA_VERSION: i64 = MIGRATIONS.len() as i64;
 
-    for pkg in pantry::ls(config) {
-        for mut program in pkg.programs {
-            program = std::path::Path::new(&program)
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_string();
-            tx.execute(
-                "INSERT INTO provides (project, program) VALUES (?1, ?2);",
-                params![pkg.project, program],
-            )?;
-        }
+/// The oldest schema version whose queries still work against ours. Raise it
+/// to `SCHEMA_VERSION` when a migration drops or renames anything.
+const READABLE_BY: i64 = 1;
 
-        if let Some(display_name) = pkg.display_name {
-            tx.execute(
-                "INSERT INTO aliases (project, alias) VALUES (?1, ?2);",
-                params![pkg.project, display_name],
-            )?;
+/// Opens the db, migrating it if an older pkgx created it.
+///
+/// A db from a newer pkgx is left alone, if we can’t read it `needs_rebuild`
+/// says so and the next sync replaces it.
+pub fn open(path: &Path) -> Result<Connection, Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut conn = Connection::open(path)?;
+    conn.busy_timeout(Duration::from_secs(10))?;
+    let version = schema_version(&conn)?;
+    if version > 0 && version < SCHEMA_VERSION {
+        migrate(&mut conn)?;
+    }
+    Ok(conn)
+}
+
+/// Applies whatever migrations `conn` hasn’t had yet.
+fn migrate(conn: &mut Connection) -> Result<(), Error> {
+    // immediate so two pkgxs opening the same old db don’t both migrate it
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let version = schema_version(&tx)?;
+    for migration in MIGRATIONS.iter().skip(version.max(0) as usize) {
+        tx.execute_batch(migration)?;
+    }
+    if version < SCHEMA_VERSION {
+        tx.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        tx.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES ('readable_by', ?1);",
+            params![READABLE_BY.to_string()],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn schema_version(conn: &Connection) -> Result<i64, Error> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
+
+/// Whether the db is empty, predates versioning, has been invalidated by a
+/// migration or was written by a newer pkgx in a schema we can’t read.
+pub fn needs_rebuild(conn: &Connection) -> Result<bool, Error> {
+    let version = schema_version(conn)?;
+    if version == 0 {
+        return Ok(true);
+    }
+    if version > SCHEMA_VERSION {
+        let readable_by: Option<String> = conn
+            .query_row(
+                "SELECT value FROM meta WHERE key = 'readable_by'",
+                [],
+                |row| row.get(0),
+            )
+            .ok();
+        let readable = readable_by
+            .and_then(|v| v.parse::<i64>().ok())
+            .is_some_and(|v| v <= SCHEMA_VERSION);
+        if !readable {
+            return Ok(true);
         }
+    }
+    let projects: i64 = conn.query_row("SELECT COUNT(*) FROM projects", [], |row| row.get(0))?;
+    Ok(projects == 0)
+}
+
+/// Rebuilds the db from the pantry, only re-parsing `package.yml`s that
+/// changed since the last time.
+///
+/// The new db is built alongside and renamed over the old one so concurrent
+/// readers see either the old or the new db, never one half built. `conn` is
+/// reopened on the new db.
+pub fn cache(config: &Config, conn: &mut Connection) -> Result<(), Error> {
+    let tmp = config.pantry_db_file.with_file_name(format!(
+        "{}.{}.tmp",
+        config
+            .pantry_db_file
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy(),
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&tmp);
+
+    // rows are copied across as is, so only from a db with the same schema
+    let old_hashes = if schema_version(conn)? == SCHEMA_VERSION {
+        hashes(conn)
+    } else {
+        HashMap::new()
+    };
+
+    let mut new = Connection::open(&tmp)?;
+    new.execute_batch(
+        "
+    PRAGMA synchronous = OFF;
+    PRAGMA journal_mode = MEMORY;
+    PRAGMA temp_store = MEMORY;
+    CREATE TEMP TABLE unchanged (project TEXT PRIMARY KEY);
+    ",
+    )?;
+    migrate(&mut new)?;
+
+    // unchanged projects are copied across from the old db
+    if !old_hashes.is_empty() {
+        new.execute(
+            "ATTACH DATABASE ?1 AS old;",
+            params![config.pantry_db_file.to_string_lossy()],
+        )?;
+    }
+
+    let tx = new.transaction()?;
 
-        for dep in pkg.deps {
+    for file in pantry::files(config) {
+        let Ok(content) = fs::read(&file.path) else {
+            continue;
+        };
+        let hash = format!("{:x}", Sha256::digest(&content));
+        let pantry = file.pantry.to_string_lossy().to_string();
+
+        // a project that moved to another pantry may be unchanged yet is re-parsed
+        if old_hashes.get(&file.project) == Some(&(hash.clone(), pantry.clone())) {
             tx.execute(
-                "INSERT INTO dependencies (project, pkgspec) VALUES (?1, ?2);",
-                params![pkg.project, dep.to_string()],
+                "INSERT INTO unchanged (project) VALUES (?1);",
+                params![file.project],
             )?;
+        } else {
+            match PantryEntry::from_file(&file, &config.platform) {
+                Ok(pkg) => insert(&tx, pkg)?,
+                Err(err) => {
+                    // `pantry::lint` reports these in full
+                    if cfg!(debug_assertions) {
+                        eprintln!("parse failure: {:?}: {}", file.path, err);
+                    }
+                    continue;
+                }
+            }
         }
 
-        for companion in pkg.companions {
+        tx.execute(
+            "INSERT INTO projects (project, hash, pantry) VALUES (?1, ?2, ?3);",
+            params![file.project, hash, pantry],
+        )?;
+    }
+
+    if !old_hashes.is_empty() {
+        for table in [
+            "provides",
+            "dependencies",
+            "companions",
+            "runtime_env",
+            "runtime_paths",
+            "aliases",
+            "metadata",
+            "platforms",
+            "build_dependencies",
+            "search",
+        ] {
             tx.execute(
-                "INSERT INTO companions (project, pkgspec) VALUES (?1, ?2);",
-                params![pkg.project, companion.to_string()],
+                &format!(
+                    "INSERT INTO main.{table} SELECT * FROM old.{table}
+                     WHERE project IN (SELECT project FROM unchanged);"
+                ),
+                [],
             )?;
         }
+    }
+
+    tx.commit()?;
+    if !old_hashes.is_empty() {
+        new.execute("DETACH DATABASE old;", [])?;
+    }
+    new.close().map_err(|(_, err)| err)?;
+
+    // nothing may hold the old db open while we replace it (Windows forbids it)
+    *conn = Connection::open_in_memory()?;
+    fs::rename(&tmp, &config.pantry_db_file)?;
+    *conn = open(&config.pantry_db_file)?;
 
-        for (key, value) in pkg.env {
+    Ok(())
+}
+
+/// The content hash of every `package.yml` the db was last built from and
+/// the pantry it was in.
+fn hashes(conn: &Connection) -> HashMap<String, (String, String)> {
+    // an empty or older db has no hashes, then everything is parsed
+    let Ok(mut stmt) = conn.prepare("SELECT project, hash, pantry FROM projects") else {
+        return HashMap::new();
+    };
+    stmt.query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+        .map(|rows| rows.filter_map(Result::ok).collect())
+        .unwrap_or_default()
+}
+
+fn insert(tx: &Transaction, pkg: PantryEntry) -> Result<(), Error> {
+    let programs: Vec<String> = pkg
+        .programs
+        .iter()
+        .map(|program| {
+            std::path::Path::new(program)
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+
+    tx.execute(
+        "INSERT INTO search (project, programs, names, description) VALUES (?1, ?2, ?3, ?4);",
+        params![
+            pkg.project,
+            programs.join(" "),
+            pkg.display_name.clone().unwrap_or_default(),
+            pkg.description.clone().unwrap_or_default()
+        ],
+    )?;
+
+    for program in programs {
+        tx.execute(
+            "INSERT INTO provides (project, program) VALUES (?1, ?2);",
+            params![pkg.project, program],
+        )?;
+    }
+
+    if let Some(display_name) = pkg.display_name {
+        tx.execute(
+            "INSERT INTO aliases (project, alias) VALUES (?1, ?2);",
+            params![pkg.project, display_name],
+        )?;
+    }
+
+    for dep in pkg.deps {
+        tx.execute(
+            "INSERT INTO dependencies (project, pkgspec) VALUES (?1, ?2);",
+            params![pkg.project, dep.to_string()],
+        )?;
+    }
+
+    for companion in pkg.companions {
+        tx.execute(
+            "INSERT INTO companions (project, pkgspec) VALUES (?1, ?2);",
+            params![pkg.project, companion.to_string()],
+        )?;
+    }
+
+    for (key, value) in pkg.env {
+        tx.execute(
+            "INSERT INTO runtime_env (project, envline) VALUES (?1, ?2);",
+            params![pkg.project, format!("{}={}", key, value)],
+        )?;
+    }
+
+    for (key, suffixes) in pkg.paths {
+        for suffix in suffixes {
             tx.execute(
-                "INSERT INTO runtime_env (project, envline) VALUES (?1, ?2);",
-                params![pkg.project, format!("{}={}", key, value)],
+                "INSERT INTO runtime_paths (project, key, suffix) VALUES (?1, ?2, ?3);",
+                params![pkg.project, key, suffix],
             )?;
         }
     }
 
-    tx.commit()?;
+    tx.execute(
+        "INSERT INTO metadata (project, description, homepage, license) VALUES (?1, ?2, ?3, ?4);",
+        params![pkg.project, pkg.description, pkg.homepage, pkg.license],
+    )?;
+
+    for platform in pkg.platforms {
+        tx.execute(
+            "INSERT INTO platforms (project, platform) VALUES (?1, ?2);",
+            params![pkg.project, platform],
+        )?;
+    }
+
+    for dep in pkg.build_deps {
+        tx.execute(
+            "INSERT INTO build_dependencies (project, pkgspec) VALUES (?1, ?2);",
+            params![pkg.project, dep.to_string()],
+        )?;
+    }
 
     Ok(())
 }
 
-pub fn deps_for_project(
-    project: &String,
-    conn: &Connection,
-) -> Result<Vec<PackageReq>, Box<dyn Error>> {
+pub fn deps_for_project(project: &String, conn: &Connection) -> Result<Vec<PackageReq>, Error> {
     let mut stmt = conn.prepare("SELECT pkgspec FROM dependencies WHERE project = ?1")?;
-    let rv = stmt.query_map(params![project], |row| {
-        let pkgspec: String = row.get(0)?;
-        let pkgrq = PackageReq::parse(&pkgspec).unwrap(); //FIXME unwrap()
-        Ok(pkgrq)
-    })?;
-    Ok(rv.collect::<Result<Vec<_>, _>>()?)
+    let pkgspecs = stmt
+        .query_map(params![project], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    pkgspecs
+        .iter()
+        .map(|pkgspec| PackageReq::parse(pkgspec))
+        .collect()
+}
+
+/// Everything the pantry says about a project.
+#[derive(Debug, Serialize)]
+pub struct Info {
+    pub project: String,
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    pub license: Option<String>,
+    /// empty if it is available everywhere
+    pub platforms: Vec<String>,
+    pub programs: Vec<String>,
+    pub dependencies: Vec<String>,
+    pub build_dependencies: Vec<String>,
+    pub companions: Vec<String>,
+    pub pantry: Option<PathBuf>,
 }
 
-pub fn which(cmd: &String, conn: &Connection) -> Result<Vec<String>, rusqlite::Error> {
+pub fn info(project: &String, conn: &Connection) -> Result<Option<Info>, Error> {
+    let metadata = conn
+        .query_row(
+            "SELECT description, homepage, license FROM metadata WHERE project = ?1",
+            params![project],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+    let Some((description, homepage, license)) = metadata else {
+        return Ok(None);
+    };
+
+    let strings = |sql: &str| -> Result<Vec<String>, Error> {
+        let mut stmt = conn.prepare(sql)?;
+        let mut rv = stmt
+            .query_map(params![project], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        rv.sort();
+        Ok(rv)
+    };
+
+    Ok(Some(Info {
+        project: project.clone(),
+        display_name: strings("SELECT alias FROM aliases WHERE project = ?1")?
+            .into_iter()
+            .next(),
+        description,
+        homepage,
+        license,
+        platforms: strings("SELECT platform FROM platforms WHERE project = ?1")?,
+        programs: strings("SELECT program FROM provides WHERE project = ?1")?,
+        dependencies: strings("SELECT pkgspec FROM dependencies WHERE project = ?1")?,
+        build_dependencies: strings("SELECT pkgspec FROM build_dependencies WHERE project = ?1")?,
+        companions: strings("SELECT pkgspec FROM companions WHERE project = ?1")?,
+        pantry: pantry_for_project(project, conn)?,
+    }))
+}
+
+/// A project that matched a search.
+#[derive(Debug, Serialize)]
+pub struct Hit {
+    pub project: String,
+    pub programs: Vec<String>,
+    pub display_name: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Projects whose name, programs, display name or description contain words
+/// starting with each word of `query`, best matches first.
+pub fn search(query: &str, limit: usize, conn: &Connection) -> Result<Vec<Hit>, Error> {
+    // quoted so FTS5 syntax in `query` is taken literally
+    let terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("\"{}\"*", term))
+        .collect();
+    if terms.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // names and programs matter more than a passing mention in a description
+    let mut stmt = conn.prepare(
+        "SELECT project, programs, names, description FROM search WHERE search MATCH ?1
+         ORDER BY bm25(search, 10.0, 10.0, 5.0, 1.0), project LIMIT ?2",
+    )?;
+    let hits = stmt
+        .query_map(params![terms.join(" "), limit as i64], |row| {
+            let optional = |s: String| (!s.is_empty()).then_some(s);
+            Ok(Hit {
+                project: row.get(0)?,
+                programs: row
+                    .get::<_, String>(1)?
+                    .split_whitespace()
+                    .map(str::to_string)
+                    .collect(),
+                display_name: optional(row.get(2)?),
+                description: optional(row.get(3)?),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(hits)
+}
+
+/// Programs and display names close to `symbol`, closest first, eg. for
+/// “did you mean” when nothing provides it.
+pub fn suggestions(symbol: &str, conn: &Connection) -> Result<Vec<String>, Error> {
+    let mut stmt = conn.prepare("SELECT program FROM provides UNION SELECT alias FROM aliases")?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // allow roughly one typo per three characters
+    let max = (symbol.chars().count() / 3).max(1);
+    let symbol = symbol.to_lowercase();
+    let mut close: Vec<(usize, String)> = names
+        .into_iter()
+        .map(|name| (edit_distance(&symbol, &name.to_lowercase()), name))
+        .filter(|(distance, _)| *distance <= max)
+        .collect();
+    close.sort();
+    close.dedup_by(|a, b| a.1 == b.1);
+    Ok(close.into_iter().take(3).map(|(_, name)| name).collect())
+}
+
+/// The pantry `project` is from, if it is in any.
+pub fn pantry_for_project(project: &String, conn: &Connection) -> Result<Option<PathBuf>, Error> {
+    let mut stmt = conn.prepare("SELECT pantry FROM projects WHERE project = ?1")?;
+    let mut rows = stmt.query(params![project])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(PathBuf::from(row.get::<_, String>(0)?))),
+        None => Ok(None),
+    }
+}
+
+/// Whether `project` runs on `platform`, ie. it has no `platforms:` or they
+/// include it.
+pub fn supported(project: &str, platform: &Platform, conn: &Connection) -> Result<bool, Error> {
+    let mut stmt = conn.prepare("SELECT platform FROM platforms WHERE project = ?1")?;
+    let platforms = stmt
+        .query_map(params![project], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(platforms.is_empty() || platforms.iter().any(|p| platform.matches(p)))
+}
+
+/// Errors with `Error::Unsupported` if `project` doesn’t run on `platform`.
+pub fn ensure_supported(
+    project: &str,
+    platform: &Platform,
+    conn: &Connection,
+) -> Result<(), Error> {
+    if supported(project, platform, conn)? {
+        Ok(())
+    } else {
+        Err(Error::Unsupported {
+            project: project.to_string(),
+            platform: platform.to_string(),
+        })
+    }
+}
+
+pub fn which(cmd: &String, conn: &Connection) -> Result<Vec<String>, Error> {
     let mut stmt = conn.prepare("SELECT project FROM provides WHERE program = ?1")?;
     let mut rv = Vec::new();
     let mut rows = stmt.query(params![cmd])?;
@@ -116,10 +573,7 @@ pub fn which(cmd: &String, conn: &Connection) -> Result<Vec<String>, rusqlite::E
     Ok(rv)
 }
 
-pub fn projects_for_symbol(
-    symbol: &String,
-    conn: &Connection,
-) -> Result<Vec<String>, rusqlite::Error> {
+pub fn projects_for_symbol(symbol: &String, conn: &Connection) -> Result<Vec<String>, Error> {
     let mut stmt = conn.prepare(
         "
         SELECT project FROM provides WHERE program = ?1
@@ -137,23 +591,36 @@ pub fn projects_for_symbol(
 pub fn runtime_env_for_project(
     project: &String,
     conn: &Connection,
-) -> Result<HashMap<String, String>, Box<dyn Error>> {
+) -> Result<HashMap<String, String>, Error> {
     let sql = "SELECT envline FROM runtime_env WHERE project = ?1";
     let mut stmt = conn.prepare(sql)?;
     let mut rows = stmt.query(params![project])?;
     let mut env = HashMap::new();
     while let Some(row) = rows.next()? {
         let envline: String = row.get(0)?;
-        let (key, value) = envline.split_once('=').unwrap();
+        let (key, value) = envline
+            .split_once('=')
+            .ok_or_else(|| Error::Parse(format!("invalid env for {}: {}", project, envline)))?;
         env.insert(key.to_string(), value.to_string());
     }
     Ok(env)
 }
 
+/// What `project` adds to path-like variables as `(key, suffix)`, in order.
+pub fn runtime_paths_for_project(
+    project: &String,
+    conn: &Connection,
+) -> Result<Vec<(String, String)>, Error> {
+    let mut stmt =
+        conn.prepare("SELECT key, suffix FROM runtime_paths WHERE project = ?1 ORDER BY rowid")?;
+    let rows = stmt.query_map(params![project], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
 pub fn companions_for_projects(
     projects: &[String],
     conn: &Connection,
-) -> Result<Vec<PackageReq>, Box<dyn Error>> {
+) -> Result<Vec<PackageReq>, Error> {
     if projects.is_empty() {
         return Ok(Vec::new());
     }
@@ -167,23 +634,21 @@ pub fn companions_for_projects(
 
     let mut stmt = conn.prepare(&query)?;
 
-    let companions = stmt.query_map(
-        rusqlite::params_from_iter(projects.iter()), // Efficiently bind the projects
-        |row| {
-            let pkgspec: String = row.get(0)?;
-            let pkgrq = PackageReq::parse(&pkgspec).unwrap(); //TODO handle error!
-            Ok(pkgrq)
-        },
-    )?;
+    let pkgspecs = stmt
+        .query_map(
+            rusqlite::params_from_iter(projects.iter()), // Efficiently bind the projects
+            |row| row.get::<_, String>(0),
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
 
-    // Collect results into a Vec<PackageReq>, propagating errors
-    Ok(companions.collect::<Result<Vec<_>, _>>()?)
+    // Parse into a Vec<PackageReq>, propagating errors
+    pkgspecs
+        .iter()
+        .map(|pkgspec| PackageReq::parse(pkgspec))
+        .collect()
 }
 
-pub fn programs_for_project(
-    project: &String,
-    conn: &Connection,
-) -> Result<Vec<String>, rusqlite::Error> {
+pub fn programs_for_project(project: &String, conn: &Connection) -> Result<Vec<String>, Error> {
     let mut stmt = conn.prepare("SELECT program FROM provides WHERE project = ?1")?;
     let mut rv = Vec::new();
     let mut rows = stmt.query(params![project])?;
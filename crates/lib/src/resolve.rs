@@ -1,7 +1,8 @@
 use crate::config::Config;
+use crate::error::Error;
+use crate::hydrate::{self, Policy, Provider};
 use crate::types::{Installation, Package, PackageReq};
-use crate::{cellar, inventory};
-use std::error::Error;
+use crate::{cellar, inventory, Version};
 
 #[derive(Debug, Default)]
 pub struct Resolution {
@@ -13,15 +14,114 @@ pub struct Resolution {
 
     /// these are the pkgs that aren’t yet installed
     pub pending: Vec<Package>,
+
+    /// the requirement each pkg was picked to satisfy, shallowest first
+    pub graph: Vec<PackageReq>,
+}
+
+/// Resolves `reqs` and their dependencies to concrete versions, preferring
+/// what is already installed and backtracking past versions that conflict.
+pub async fn solve<F>(
+    reqs: &[PackageReq],
+    policy: &Policy,
+    config: &Config,
+    get_deps: F,
+) -> Result<Resolution, Error>
+where
+    F: Fn(&Package) -> Result<Vec<PackageReq>, Error>,
+{
+    let mut provider = Cellar {
+        config,
+        get_deps,
+        missing: vec![],
+    };
+    let picks = loop {
+        let reqs: Vec<_> = reqs
+            .iter()
+            .filter(|req| !provider.missing.contains(&req.to_string()))
+            .cloned()
+            .collect();
+        match hydrate::solve(&reqs, policy, &provider).await {
+            // offline we carry on without it so everything missing is reported at once
+            Err(Error::NotFound(req))
+                if config.offline && !provider.missing.contains(&req.to_string()) =>
+            {
+                provider.missing.push(req.to_string())
+            }
+            picks => break picks?,
+        }
+    };
+
+    let mut rv = Resolution::default();
+    for pick in picks {
+        let path = cellar::dst(&pick.pkg, config);
+        if path.is_dir() {
+            rv.installed.push(Installation {
+                path,
+                pkg: pick.pkg.clone(),
+            });
+        } else {
+            rv.pending.push(pick.pkg.clone());
+        }
+        rv.pkgs.push(pick.pkg);
+        rv.graph.push(pick.req);
+    }
+
+    if config.offline {
+        let mut missing = provider.missing;
+        missing.extend(rv.pending.iter().map(|pkg| pkg.to_string()));
+        if !missing.is_empty() {
+            return Err(Error::Offline(missing));
+        }
+    }
+    Ok(rv)
+}
+
+struct Cellar<'a, F> {
+    config: &'a Config,
+    get_deps: F,
+    /// requirements we can’t satisfy offline, left out so we can find the rest
+    missing: Vec<String>,
+}
+
+impl<F> Provider for Cellar<'_, F>
+where
+    F: Fn(&Package) -> Result<Vec<PackageReq>, Error>,
+{
+    async fn installed(&self, project: &str) -> Result<Vec<Version>, Error> {
+        Ok(cellar::ls(project, self.config)
+            .await?
+            .into_iter()
+            .map(|installation| installation.pkg.version)
+            .collect())
+    }
+
+    async fn available(&self, project: &str) -> Result<Vec<Version>, Error> {
+        if self.config.offline {
+            return Ok(vec![]);
+        }
+        match inventory::ls(&project.to_string(), self.config).await {
+            Err(Error::NotFound(_)) => Ok(vec![]),
+            versions => versions,
+        }
+    }
+
+    fn deps(&self, pkg: &Package) -> Result<Vec<PackageReq>, Error> {
+        Ok((self.get_deps)(pkg)?
+            .into_iter()
+            .filter(|dep| !self.missing.contains(&dep.to_string()))
+            .collect())
+    }
 }
 
 //TODO no need to take array since it doesn’t consider anything
 use futures::stream::{FuturesUnordered, StreamExt};
 
-pub async fn resolve(
-    reqs: &Vec<PackageReq>,
-    config: &Config,
-) -> Result<Resolution, Box<dyn Error>> {
+pub async fn resolve(reqs: &Vec<PackageReq>, config: &Config) -> Result<Resolution, Error> {
+    if config.offline {
+        return resolve_offline(reqs, config).await;
+    }
+
     let mut rv = Resolution::default();
 
     // Create a FuturesUnordered to run the tasks concurrently
@@ -30,18 +130,15 @@ pub async fn resolve(
     for req in reqs {
         futures.push(async move {
             if let Some(installation) = cellar::resolve(req, config).await? {
-                Ok::<_, Box<dyn Error>>((
-                    Some((installation.clone(), installation.pkg.clone())),
-                    None,
-                ))
+                Ok::<_, Error>((Some((installation.clone(), installation.pkg.clone())), None))
             } else if let Some(version) = inventory::select(req, config).await? {
                 let pkg = Package {
                     project: req.project.clone(),
                     version,
                 };
-                Ok::<_, Box<dyn Error>>((None, Some(pkg)))
+                Ok::<_, Error>((None, Some(pkg)))
             } else {
-                Err(Box::new(ResolveError { pkg: req.clone() }) as Box<dyn Error>)
+                Err(Error::NotFound(req.clone()))
             }
         });
     }
@@ -64,16 +161,23 @@ pub async fn resolve(
     Ok(rv)
 }
 
-use std::fmt;
+// offline we can only use what is already installed
+async fn resolve_offline(reqs: &Vec<PackageReq>, config: &Config) -> Result<Resolution, Error> {
+    let mut rv = Resolution::default();
+    let mut missing = vec![];
 
-#[derive(Debug)]
-pub struct ResolveError {
-    pub pkg: PackageReq, // Holds the package or requirement
-}
-impl Error for ResolveError {}
+    for req in reqs {
+        if let Some(installation) = cellar::resolve(req, config).await? {
+            rv.pkgs.push(installation.pkg.clone());
+            rv.installed.push(installation);
+        } else {
+            missing.push(req.to_string());
+        }
+    }
 
-impl fmt::Display for ResolveError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "not-found: pkg: {:?}", self.pkg)
+    if missing.is_empty() {
+        Ok(rv)
+    } else {
+        Err(Error::Offline(missing))
     }
 }
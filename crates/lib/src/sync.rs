@@ -1,60 +1,67 @@
-use crate::{client::build_client, config::Config, pantry_db};
+use crate::{client::build_client, config::Config, download, error::Error, pantry_db};
 use async_compression::tokio::bufread::XzDecoder;
 use fs2::FileExt;
 use futures::TryStreamExt;
 use rusqlite::Connection;
-use std::{error::Error, fs::OpenOptions, path::PathBuf};
+use std::fs::OpenOptions;
 use tokio_tar::ArchiveBuilder;
 use tokio_util::compat::FuturesAsyncReadCompatExt;
 
-#[allow(clippy::all)]
-pub fn should(config: &Config) -> Result<bool, Box<dyn Error>> {
+pub fn should(config: &Config, conn: &Connection) -> Result<bool, Error> {
     if !config.pantry_dir.join("projects").is_dir() {
         Ok(true)
     } else {
-        // the file always exists because we create the connection
-        // but will be 0 bytes if we need to fill it
-        Ok(std::fs::metadata(&config.pantry_db_file)?.len() == 0)
+        pantry_db::needs_rebuild(conn)
     }
 }
 
 // doesn’t replace pantry clone, will build db
 // essential for working in a local pantry clone with PKGX_PANTRY_DIR set
-pub async fn ensure(config: &Config, conn: &mut Connection) -> Result<(), Box<dyn Error>> {
+pub async fn ensure(config: &Config, conn: &mut Connection) -> Result<(), Error> {
     if !config.pantry_dir.join("projects").is_dir() {
         replace(config, conn).await
     } else {
-        let lockfile = lock(config)?;
-        pantry_db::cache(config, conn)?;
-        FileExt::unlock(&lockfile)?;
-        Ok(())
+        rebuild(config, conn)
     }
 }
 
-pub async fn update(config: &Config, conn: &mut Connection) -> Result<(), Box<dyn Error>> {
+pub async fn update(config: &Config, conn: &mut Connection) -> Result<(), Error> {
     if std::env::var("PKGX_PANTRY_DIR").is_ok() {
-        return Err("PKGX_PANTRY_DIR is set, refusing to update pantry")?;
+        // we don’t replace a local pantry but it or an overlay may have changed
+        rebuild(config, conn)
+    } else {
+        replace(config, conn).await
     }
-    replace(config, conn).await
 }
 
-async fn replace(config: &Config, conn: &mut Connection) -> Result<(), Box<dyn Error>> {
-    let url = format!(
-        "{}/{}",
-        config.dist_url,
-        env!("PKGX_PANTRY_TARBALL_FILENAME")
-    );
+fn rebuild(config: &Config, conn: &mut Connection) -> Result<(), Error> {
+    let lockfile = lock(config)?;
+    pantry_db::cache(config, conn)?;
+    FileExt::unlock(&lockfile)?;
+    Ok(())
+}
+
+async fn replace(config: &Config, conn: &mut Connection) -> Result<(), Error> {
+    if config.offline {
+        return Err(Error::Offline(vec!["pantry".to_string()]));
+    }
 
     let lockfile = lock(config)?;
-    download_and_extract_pantry(&url, &config.pantry_dir).await?;
+    download_and_extract_pantry(config).await?;
     pantry_db::cache(config, conn)?;
     FileExt::unlock(&lockfile)?;
 
     Ok(())
 }
 
-async fn download_and_extract_pantry(url: &str, dest: &PathBuf) -> Result<(), Box<dyn Error>> {
-    let rsp = build_client()?.get(url).send().await?.error_for_status()?;
+async fn download_and_extract_pantry(config: &Config) -> Result<(), Error> {
+    let client = build_client()?;
+    let (rsp, _) = download::get_mirrored(
+        &config.dist_urls,
+        env!("PKGX_PANTRY_TARBALL_FILENAME"),
+        |url| client.get(url),
+    )
+    .await?;
 
     let stream = rsp.bytes_stream();
 
@@ -67,12 +74,12 @@ async fn download_and_extract_pantry(url: &str, dest: &PathBuf) -> Result<(), Bo
     let mut archive = ArchiveBuilder::new(decoder)
         .set_preserve_permissions(true)
         .build();
-    archive.unpack(dest).await?;
+    archive.unpack(&config.pantry_dir).await?;
 
     Ok(())
 }
 
-fn lock(config: &Config) -> Result<std::fs::File, Box<dyn Error>> {
+fn lock(config: &Config) -> Result<std::fs::File, Error> {
     std::fs::create_dir_all(&config.pantry_dir)?;
     #[cfg(not(windows))]
     let lockfile = OpenOptions::new().read(true).open(&config.pantry_dir)?;
@@ -83,6 +90,8 @@ fn lock(config: &Config) -> Result<std::fs::File, Box<dyn Error>> {
         .truncate(true)
         .write(true)
         .open(config.pantry_dir.join("lockfile"))?;
-    lockfile.lock_exclusive()?;
+    lockfile
+        .lock_exclusive()
+        .map_err(|_| Error::Locked(config.pantry_dir.clone()))?;
     Ok(lockfile)
 }
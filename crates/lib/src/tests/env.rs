@@ -0,0 +1,149 @@
+use std::{fs, path::PathBuf};
+
+use rusqlite::Connection;
+
+use super::{config, write_package};
+use crate::{
+    env::{map, EnvValue, Paths},
+    pantry_db,
+    types::{Installation, Package},
+    Version,
+};
+
+fn value(
+    default: Option<&str>,
+    prepend: Option<(&str, &str)>,
+    append: Option<(&str, &str)>,
+) -> EnvValue {
+    let pair = |(a, b): (&str, &str)| (a.to_string(), b.to_string());
+    EnvValue {
+        default: default.map(str::to_string),
+        prepend: prepend.map(pair),
+        append: append.map(pair),
+    }
+}
+
+#[test]
+fn test_parse() {
+    assert_eq!(
+        EnvValue::parse("CFLAGS", "-I/a/include $CFLAGS"),
+        value(None, Some(("-I/a/include", " ")), None)
+    );
+    assert_eq!(
+        EnvValue::parse("FOO", "${FOO}:/a"),
+        value(None, None, Some((":", "/a")))
+    );
+    assert_eq!(
+        EnvValue::parse("FOO", "/a:${FOO:-/b}; /c"),
+        value(Some("/b"), Some(("/a", ":")), Some(("; ", "/c")))
+    );
+    // only the parent value means anything
+    assert_eq!(
+        EnvValue::parse("FOO", "it's \"$5\" $FOOBAR `x`"),
+        value(Some("it's \"$5\" $FOOBAR `x`"), None, None)
+    );
+}
+
+#[test]
+fn test_then() {
+    let mut rv = EnvValue::prepend_paths(&["/a/bin".to_string()]);
+    // we have a value so the default doesn’t apply
+    rv.then(EnvValue::parse("PATH", "/x"));
+    rv.then(EnvValue::parse("PATH", "/b/bin:$PATH:/c/bin"));
+    rv.then(EnvValue::parse("PATH", "$PATH:/d/bin"));
+
+    let sep = crate::env::SEP;
+    assert_eq!(
+        rv,
+        value(
+            None,
+            Some(("/b/bin:/a/bin", sep)),
+            Some((":", "/c/bin:/d/bin"))
+        )
+    );
+
+    let mut rv = EnvValue::default();
+    rv.then(EnvValue::parse("FOO", "/x"));
+    rv.then(EnvValue::parse("FOO", "/y"));
+    assert_eq!(rv, value(Some("/x"), None, None));
+}
+
+#[test]
+fn test_eval() {
+    let rv = value(Some("/d"), Some(("/a", ":")), Some((":", "/c")));
+    assert_eq!(rv.eval(None), "/a:/d:/c");
+    assert_eq!(rv.eval(Some("")), "/a:/d:/c");
+    assert_eq!(rv.eval(Some("/p")), "/a:/p:/c");
+
+    let rv = value(None, Some(("-I/a", " ")), None);
+    assert_eq!(rv.eval(None), "-I/a");
+    assert_eq!(rv.eval(Some("-O2")), "-I/a -O2");
+}
+
+#[test]
+fn test_paths() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+    write_package(
+        &config,
+        "python.org",
+        "runtime:\n  paths:\n    PYTHONPATH: lib/python{{version.marketing}}/site-packages\n",
+    );
+    write_package(&config, "cmake.org", "provides: [bin/cmake]\n");
+    let mut conn = Connection::open(&config.pantry_db_file).unwrap();
+    pantry_db::cache(&config, &mut conn).unwrap();
+
+    let installation = |project: &str, version: &str, dirs: &[&str]| {
+        let path = config.pkgx_dir.join(project).join(format!("v{}", version));
+        for dir in dirs {
+            fs::create_dir_all(path.join(dir)).unwrap();
+        }
+        Installation {
+            path,
+            pkg: Package {
+                project: project.to_string(),
+                version: Version::parse(version).unwrap(),
+            },
+        }
+    };
+    let installations = vec![
+        installation(
+            "python.org",
+            "3.11.4",
+            &["bin", "lib/python3.11/site-packages"],
+        ),
+        installation("numpy.org", "1.26.0", &["lib/python3.11/site-packages"]),
+        installation("cmake.org", "3.28.0", &["bin"]),
+    ];
+    let dirs = |suffix: &str, projects: &[usize]| -> Vec<String> {
+        let suffix = PathBuf::from(suffix);
+        projects
+            .iter()
+            .map(|i| installations[*i].path.join(&suffix))
+            .map(|path| path.to_string_lossy().trim_end_matches('/').to_string())
+            .collect()
+    };
+
+    let paths = Paths::load(&installations, &conn, &config).unwrap();
+    let env = map(&installations, &paths);
+    assert_eq!(env["PATH"], dirs("bin", &[0, 2]));
+    assert_eq!(
+        env["PYTHONPATH"],
+        dirs("lib/python3.11/site-packages", &[0, 1])
+    );
+    assert_eq!(env["CMAKE_PREFIX_PATH"], dirs("", &[0, 1, 2]));
+
+    // the user’s say replaces ours
+    config.env_paths.insert("PATH".to_string(), vec![]);
+    config
+        .env_paths
+        .insert("CMAKE_PREFIX_PATH".to_string(), vec![]);
+    config
+        .env_paths
+        .insert("GOPATH".to_string(), vec![".".to_string()]);
+    let paths = Paths::load(&installations, &conn, &config).unwrap();
+    let env = map(&installations, &paths);
+    assert_eq!(env.get("PATH"), None);
+    assert_eq!(env.get("CMAKE_PREFIX_PATH"), None);
+    assert_eq!(env["GOPATH"], dirs("", &[0, 1, 2]));
+}
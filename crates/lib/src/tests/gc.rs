@@ -0,0 +1,127 @@
+use std::{
+    fs,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use super::config;
+use crate::{gc, types::PackageReq};
+
+// installed and last used long ago
+fn unused(path: &Path) {
+    fs::create_dir_all(path).unwrap();
+    let name = path.file_name().unwrap().to_string_lossy();
+    let mark = fs::File::create(path.with_file_name(format!(".{}.used", name))).unwrap();
+    mark.set_modified(SystemTime::UNIX_EPOCH).unwrap();
+}
+
+#[tokio::test]
+async fn test_gc_keeps_roots_and_their_deps() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+
+    for path in [
+        "example.com/v1.0.0",
+        "example.com/v2.0.0",
+        "github.com/foo/bar/v3.0.0",
+        "example.org/v1.0.0",
+    ] {
+        unused(&tmp.path().join(path));
+    }
+    #[cfg(unix)]
+    for (name, target) in [("v*", "v2.0.0"), ("v2", "v2.0.0"), ("v1", "v1.0.0")] {
+        std::os::unix::fs::symlink(target, tmp.path().join("example.com").join(name)).unwrap();
+    }
+
+    let roots = vec![PackageReq::parse("example.com^1").unwrap()];
+    let garbage = gc::garbage(&roots, Duration::from_secs(60), &config, |project| {
+        Ok(match project {
+            "example.com" => vec![PackageReq::parse("github.com/foo/bar^3").unwrap()],
+            _ => vec![],
+        })
+    })
+    .await
+    .unwrap();
+
+    let mut paths: Vec<_> = garbage.iter().map(|i| i.path.clone()).collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![
+            tmp.path().join("example.com/v2.0.0"),
+            tmp.path().join("example.org/v1.0.0"),
+        ]
+    );
+
+    for installation in &garbage {
+        gc::remove(installation, &config).await.unwrap();
+    }
+    assert!(!tmp.path().join("example.com/v2.0.0").exists());
+    assert!(tmp.path().join("example.com/v1.0.0").is_dir());
+
+    #[cfg(unix)]
+    {
+        let shelf = tmp.path().join("example.com");
+        assert!(!shelf.join("v2").is_symlink());
+        assert_eq!(
+            fs::read_link(shelf.join("v*")).unwrap(),
+            std::path::PathBuf::from("v1.0.0")
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_gc_leaves_version_dirs_inside_a_shelf() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+
+    for path in [
+        "example.com/v1.0.0",
+        // the project’s data, not installations
+        "example.com/var/cache/v2.0.0",
+        "example.com/share/v3",
+    ] {
+        unused(&tmp.path().join(path));
+    }
+
+    let garbage = gc::garbage(&[], Duration::from_secs(60), &config, |_| Ok(vec![]))
+        .await
+        .unwrap();
+
+    let paths: Vec<_> = garbage.iter().map(|i| i.path.clone()).collect();
+    assert_eq!(paths, vec![tmp.path().join("example.com/v1.0.0")]);
+
+    for installation in &garbage {
+        gc::remove(installation, &config).await.unwrap();
+    }
+    assert!(tmp.path().join("example.com/var/cache/v2.0.0").is_dir());
+    assert!(tmp.path().join("example.com/share/v3").is_dir());
+}
+
+#[tokio::test]
+async fn test_gc_keeps_installations_without_a_use_mark() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+
+    unused(&tmp.path().join("example.com/v1.0.0"));
+    // as installed by a pkgx that didn’t mark use, with the bottle’s mtime
+    let old = tmp.path().join("example.com/v2.0.0");
+    fs::create_dir_all(&old).unwrap();
+    fs::File::open(&old)
+        .unwrap()
+        .set_modified(SystemTime::UNIX_EPOCH)
+        .unwrap();
+
+    let garbage = gc::garbage(&[], Duration::from_secs(60), &config, |_| Ok(vec![]))
+        .await
+        .unwrap();
+    let paths: Vec<_> = garbage.iter().map(|i| i.path.clone()).collect();
+    assert_eq!(paths, vec![tmp.path().join("example.com/v1.0.0")]);
+
+    // once used it is kept for as long as anything else
+    gc::mark_used(&garbage);
+    let garbage = gc::garbage(&[], Duration::from_secs(60), &config, |_| Ok(vec![]))
+        .await
+        .unwrap();
+    assert!(garbage.is_empty());
+}
@@ -0,0 +1,248 @@
+use crate::{
+    error::Error,
+    hydrate::{solve, Pick, Policy, Provider},
+    types::{Package, PackageReq},
+    Version,
+};
+
+#[tokio::test]
+async fn test_conflict_reports_both_paths() {
+    let reqs = vec![
+        PackageReq::parse("nodejs.org@18").unwrap(),
+        PackageReq::parse("curl.se").unwrap(),
+    ];
+    let inventory = Inventory {
+        installed: vec![],
+        available: vec![
+            ("nodejs.org=18.0.0", vec!["openssl.org^1.1"]),
+            ("curl.se=8.0.0", vec!["openssl.org^3"]),
+            ("openssl.org=1.1.1", vec![]),
+            ("openssl.org=3.0.0", vec![]),
+        ],
+    };
+    let err = solve(&reqs, &Policy::default(), &inventory)
+        .await
+        .unwrap_err();
+
+    let Error::Conflict(conflict) = err else {
+        panic!("expected a conflict, got {:?}", err);
+    };
+    assert_eq!(conflict.project, "openssl.org");
+
+    let mut paths = [conflict.a.clone(), conflict.b.clone()].map(|path| {
+        path.iter()
+            .map(|req| req.to_string())
+            .collect::<Vec<_>>()
+            .join(" → ")
+    });
+    paths.sort();
+    assert_eq!(
+        paths,
+        [
+            "curl.se → openssl.org^3".to_string(),
+            "nodejs.org^18 → openssl.org^1.1".to_string(),
+        ]
+    );
+
+    assert_eq!(
+        serde_json::to_value(&*conflict).unwrap(),
+        serde_json::json!({
+            "project": "openssl.org",
+            "paths": [
+                ["nodejs.org^18", "openssl.org^1.1"],
+                ["curl.se", "openssl.org^3"],
+            ],
+        })
+    );
+}
+
+/// An inventory where dependencies differ by version.
+struct Inventory {
+    installed: Vec<&'static str>,
+    available: Vec<(&'static str, Vec<&'static str>)>,
+}
+
+impl Provider for Inventory {
+    async fn installed(&self, project: &str) -> Result<Vec<Version>, Error> {
+        Ok(versions(&self.installed, project))
+    }
+
+    async fn available(&self, project: &str) -> Result<Vec<Version>, Error> {
+        let pkgs: Vec<_> = self.available.iter().map(|(pkg, _)| *pkg).collect();
+        Ok(versions(&pkgs, project))
+    }
+
+    fn deps(&self, pkg: &Package) -> Result<Vec<PackageReq>, Error> {
+        let spec = format!("{}={}", pkg.project, pkg.version);
+        Ok(self
+            .available
+            .iter()
+            .find(|(pkg, _)| *pkg == spec)
+            .map(|(_, deps)| {
+                deps.iter()
+                    .map(|dep| PackageReq::parse(dep).unwrap())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+fn versions(pkgs: &[&str], project: &str) -> Vec<Version> {
+    pkgs.iter()
+        .filter_map(|pkg| pkg.split_once('='))
+        .filter(|(name, _)| *name == project)
+        .map(|(_, version)| Version::parse(version).unwrap())
+        .collect()
+}
+
+fn picked(picks: &[Pick]) -> Vec<String> {
+    let mut picks: Vec<_> = picks.iter().map(|pick| pick.pkg.to_string()).collect();
+    picks.sort();
+    picks
+}
+
+#[tokio::test]
+async fn test_solve_backtracks_to_older_intermediate() {
+    let inventory = Inventory {
+        installed: vec![],
+        available: vec![
+            ("a.com=2.0.0", vec!["c.com^2"]),
+            ("a.com=1.0.0", vec!["c.com^1"]),
+            ("b.com=1.0.0", vec!["c.com^1"]),
+            ("c.com=1.0.0", vec![]),
+            ("c.com=2.0.0", vec![]),
+        ],
+    };
+    let reqs = vec![
+        PackageReq::parse("a.com").unwrap(),
+        PackageReq::parse("b.com").unwrap(),
+    ];
+
+    let picks = solve(&reqs, &Policy::default(), &inventory).await.unwrap();
+    assert_eq!(
+        picked(&picks),
+        ["a.com=1.0.0", "b.com=1.0.0", "c.com=1.0.0"]
+    );
+}
+
+#[tokio::test]
+async fn test_solve_prefers_installed() {
+    let inventory = Inventory {
+        installed: vec!["a.com=1.0.0"],
+        available: vec![("a.com=1.0.0", vec![]), ("a.com=1.1.0", vec![])],
+    };
+    let reqs = vec![PackageReq::parse("a.com^1").unwrap()];
+
+    let picks = solve(&reqs, &Policy::default(), &inventory).await.unwrap();
+    assert_eq!(picked(&picks), ["a.com=1.0.0"]);
+}
+
+#[tokio::test]
+async fn test_solve_multi_version_policy() {
+    let inventory = Inventory {
+        installed: vec![],
+        available: vec![
+            ("a.com=1.0.0", vec!["unicode.org^71"]),
+            ("b.com=1.0.0", vec!["unicode.org^73"]),
+            ("unicode.org=71.1.0", vec![]),
+            ("unicode.org=73.2.0", vec![]),
+        ],
+    };
+    let reqs = vec![
+        PackageReq::parse("a.com").unwrap(),
+        PackageReq::parse("b.com").unwrap(),
+    ];
+
+    let picks = solve(&reqs, &Policy::default(), &inventory).await.unwrap();
+    assert_eq!(
+        picked(&picks),
+        [
+            "a.com=1.0.0",
+            "b.com=1.0.0",
+            "unicode.org=71.1.0",
+            "unicode.org=73.2.0"
+        ]
+    );
+
+    let err = solve(
+        &reqs,
+        &Policy {
+            multi_version: Default::default(),
+        },
+        &inventory,
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(err, Error::Conflict(conflict) if conflict.project == "unicode.org"));
+}
+
+#[tokio::test]
+async fn test_solve_not_found() {
+    let inventory = Inventory {
+        installed: vec![],
+        available: vec![("a.com=1.0.0", vec!["b.com^2"]), ("b.com=1.0.0", vec![])],
+    };
+    let reqs = vec![PackageReq::parse("a.com").unwrap()];
+
+    let err = solve(&reqs, &Policy::default(), &inventory)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::NotFound(req) if req.project == "b.com"));
+}
+
+/// Every version of a project has the same deps, as with the pantry.
+struct Unversioned {
+    deps: Vec<(&'static str, Vec<&'static str>)>,
+    calls: std::cell::Cell<usize>,
+}
+
+impl Provider for Unversioned {
+    async fn installed(&self, _: &str) -> Result<Vec<Version>, Error> {
+        Ok(vec![])
+    }
+
+    async fn available(&self, _: &str) -> Result<Vec<Version>, Error> {
+        Ok((1..=40)
+            .map(|major| Version::parse(&format!("{}.0.0", major)).unwrap())
+            .collect())
+    }
+
+    fn deps(&self, pkg: &Package) -> Result<Vec<PackageReq>, Error> {
+        self.calls.set(self.calls.get() + 1);
+        Ok(self
+            .deps
+            .iter()
+            .find(|(project, _)| *project == pkg.project)
+            .map(|(_, deps)| {
+                deps.iter()
+                    .map(|dep| PackageReq::parse(dep).unwrap())
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+}
+
+#[tokio::test]
+async fn test_solve_unresolvable_fails_fast() {
+    let provider = Unversioned {
+        deps: vec![
+            ("a.com", vec!["x.com"]),
+            ("b.com", vec!["y.com"]),
+            ("x.com", vec!["c.com^1"]),
+            ("y.com", vec!["c.com^2"]),
+        ],
+        calls: Default::default(),
+    };
+    let reqs = vec![
+        PackageReq::parse("a.com").unwrap(),
+        PackageReq::parse("b.com").unwrap(),
+    ];
+
+    let err = solve(&reqs, &Policy::default(), &provider)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Conflict(conflict) if conflict.project == "c.com"));
+    // versions with the same deps aren’t tried, so this is ~ projects × versions
+    // rather than every combination of them
+    assert!(provider.calls.get() < 200, "{} calls", provider.calls.get());
+}
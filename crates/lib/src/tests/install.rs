@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+
+use sha2::{Digest, Sha256};
+
+use super::{bottle, config, serve, serve_flaky};
+use crate::{
+    error::Error,
+    install::{install, InstallEvent, Receipt},
+    types::{Arch, Package, Platform},
+    Version,
+};
+
+fn pkg() -> Package {
+    Package {
+        project: "example.com".to_string(),
+        version: Version::parse("1.2.3").unwrap(),
+    }
+}
+
+fn routes(pkg: &Package, bottle: Vec<u8>, digest: String) -> HashMap<String, Vec<u8>> {
+    let (platform, arch) = crate::types::host();
+    let path = format!(
+        "/{}/{}/{}/v{}.tar.xz",
+        pkg.project, platform, arch, pkg.version.raw
+    );
+    let sidecar = format!("{}  v{}.tar.xz\n", digest, pkg.version.raw).into_bytes();
+    HashMap::from([(format!("{}.sha256sum", path), sidecar), (path, bottle)])
+}
+
+#[tokio::test]
+async fn test_install_verifies_checksum() {
+    let pkg = pkg();
+    let bottle = bottle(&pkg).await;
+    let digest = format!("{:x}", Sha256::digest(&bottle));
+    let url = serve(routes(&pkg, bottle, digest)).await.url;
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config(url, tmp.path().to_path_buf());
+
+    let installation = install(&pkg, &config, None::<fn(InstallEvent)>)
+        .await
+        .unwrap();
+
+    assert!(installation.path.join("bin/hi").is_file());
+}
+
+#[tokio::test]
+async fn test_install_rejects_corrupt_bottle() {
+    let pkg = pkg();
+    let bottle = bottle(&pkg).await;
+    let digest = format!("{:x}", Sha256::digest(b"something else entirely"));
+    let url = serve(routes(&pkg, bottle, digest)).await.url;
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config(url, tmp.path().to_path_buf());
+
+    let err = install(&pkg, &config, None::<fn(InstallEvent)>)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, Error::Integrity { .. }));
+    assert!(!crate::cellar::dst(&pkg, &config).exists());
+}
+
+#[tokio::test]
+async fn test_install_resumes_dropped_downloads() {
+    let pkg = pkg();
+    let bottle = bottle(&pkg).await;
+    let digest = format!("{:x}", Sha256::digest(&bottle));
+    let server = serve_flaky(routes(&pkg, bottle, digest), 2).await;
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config(server.url, tmp.path().to_path_buf());
+
+    let installation = install(&pkg, &config, None::<fn(InstallEvent)>)
+        .await
+        .unwrap();
+    assert!(installation.path.join("bin/hi").is_file());
+
+    let statuses: Vec<u16> = server
+        .log
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(path, _)| path.ends_with(".tar.xz"))
+        .map(|(_, status)| *status)
+        .collect();
+    assert_eq!(statuses, vec![200, 206, 206]);
+}
+
+#[tokio::test]
+async fn test_install_fails_over_to_next_mirror() {
+    let pkg = pkg();
+    let bottle = bottle(&pkg).await;
+    let digest = format!("{:x}", Sha256::digest(&bottle));
+    let empty = serve(HashMap::new()).await;
+    let good = serve(routes(&pkg, bottle, digest.clone())).await;
+    let tmp = tempfile::tempdir().unwrap();
+    let mut config = config(good.url.clone(), tmp.path().to_path_buf());
+    // nothing listens on port 1 and `empty` 404s everything
+    config.dist_urls = vec![
+        "http://127.0.0.1:1".to_string(),
+        empty.url.clone(),
+        good.url.clone(),
+    ];
+
+    let installation = install(&pkg, &config, None::<fn(InstallEvent)>)
+        .await
+        .unwrap();
+    assert!(installation.path.join("bin/hi").is_file());
+
+    let receipt = Receipt::read(&installation).unwrap();
+    assert_eq!(receipt.mirror, Some(good.url.clone()));
+    assert!(receipt.url.starts_with(&good.url));
+    assert_eq!(receipt.sha256, digest);
+
+    let statuses: Vec<u16> = empty.log.lock().unwrap().iter().map(|(_, s)| *s).collect();
+    assert_eq!(statuses, [404]);
+}
+
+#[tokio::test]
+async fn test_install_for_another_platform() {
+    let pkg = pkg();
+    let bottle = bottle(&pkg).await;
+    let digest = format!("{:x}", Sha256::digest(&bottle));
+    let platform: Platform = match Platform::current().arch {
+        Arch::Arm64 => "linux/x86-64",
+        Arch::X86_64 => "linux/aarch64",
+    }
+    .parse()
+    .unwrap();
+    let path = format!("/{}/{}/v{}.tar.xz", pkg.project, platform, pkg.version.raw);
+    let sidecar = format!("{}  v{}.tar.xz\n", digest, pkg.version.raw).into_bytes();
+    let routes = HashMap::from([(format!("{}.sha256sum", path), sidecar), (path, bottle)]);
+    let server = serve(routes).await;
+    let tmp = tempfile::tempdir().unwrap();
+    let mut config = config(server.url.clone(), tmp.path().to_path_buf());
+    config.platform = platform;
+
+    let installation = install(&pkg, &config, None::<fn(InstallEvent)>)
+        .await
+        .unwrap();
+    assert!(installation.path.join("bin/hi").is_file());
+    assert!(Receipt::read(&installation)
+        .unwrap()
+        .url
+        .contains(&platform.to_string()));
+}
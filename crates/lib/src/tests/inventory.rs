@@ -0,0 +1,80 @@
+use std::{collections::HashMap, time::Duration};
+
+use super::{config, serve};
+use crate::{error::Error, inventory, types::host};
+
+#[tokio::test]
+async fn test_offline_uses_cached_inventory() {
+    let (platform, arch) = host();
+    let routes = HashMap::from([(
+        format!("/example.com/{}/{}/versions.txt", platform, arch),
+        b"1.0.0\n1.1.0\n".to_vec(),
+    )]);
+    let tmp = tempfile::tempdir().unwrap();
+    let mut config = config(serve(routes).await.url, tmp.path().to_path_buf());
+
+    let online = inventory::ls(&"example.com".to_string(), &config)
+        .await
+        .unwrap();
+    assert_eq!(online.len(), 2);
+
+    config.offline = true;
+    config.dist_urls = vec!["http://127.0.0.1:1".to_string()];
+
+    let offline = inventory::ls(&"example.com".to_string(), &config)
+        .await
+        .unwrap();
+    assert_eq!(online, offline);
+
+    let err = inventory::ls(&"example.org".to_string(), &config)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, Error::Offline(_)));
+}
+
+#[tokio::test]
+async fn test_inventory_cache_revalidates_with_etag() {
+    let (platform, arch) = host();
+    let path = format!("/example.com/{}/{}/versions.txt", platform, arch);
+    let server = serve(HashMap::from([(path.clone(), b"1.0.0\n".to_vec())])).await;
+    let tmp = tempfile::tempdir().unwrap();
+    let mut config = config(server.url, tmp.path().to_path_buf());
+    let project = "example.com".to_string();
+
+    inventory::ls(&project, &config).await.unwrap();
+    inventory::ls(&project, &config).await.unwrap();
+    // second call was answered from the cache
+    assert_eq!(*server.log.lock().unwrap(), vec![(path.clone(), 200)]);
+
+    config.inventory_ttl = Duration::ZERO;
+    let versions = inventory::ls(&project, &config).await.unwrap();
+    assert_eq!(versions[0].raw, "1.0.0");
+    assert_eq!(
+        *server.log.lock().unwrap(),
+        vec![(path.clone(), 200), (path, 304)]
+    );
+}
+
+#[tokio::test]
+async fn test_inventory_etag_only_sent_to_its_mirror() {
+    let (platform, arch) = host();
+    let path = format!("/example.com/{}/{}/versions.txt", platform, arch);
+    let routes = HashMap::from([(path.clone(), b"1.0.0\n".to_vec())]);
+    let a = serve(routes.clone()).await;
+    let b = serve(routes).await;
+    let tmp = tempfile::tempdir().unwrap();
+    let mut config = config(a.url, tmp.path().to_path_buf());
+    config.inventory_ttl = Duration::ZERO;
+    let project = "example.com".to_string();
+
+    inventory::ls(&project, &config).await.unwrap();
+
+    // both serve the same ETag, but `b` didn’t issue the one we have
+    config.dist_urls = vec![b.url];
+    inventory::ls(&project, &config).await.unwrap();
+    inventory::ls(&project, &config).await.unwrap();
+    assert_eq!(
+        *b.log.lock().unwrap(),
+        vec![(path.clone(), 200), (path, 304)]
+    );
+}
@@ -0,0 +1,98 @@
+use std::{collections::HashMap, sync::Arc};
+
+use sha2::{Digest, Sha256};
+
+use super::{bottle, config, serve};
+use crate::{
+    error::Error,
+    install_multi::{install_multi_locked, ProgressBarExt},
+    lockfile::{LockedPackage, Lockfile},
+    types::{Package, PackageReq},
+    Version,
+};
+
+struct NoProgress;
+
+impl ProgressBarExt for NoProgress {
+    fn inc(&self, _: u64) {}
+    fn inc_length(&self, _: u64) {}
+}
+
+#[tokio::test]
+async fn test_lockfile_roundtrip_and_install() {
+    let pkg = Package {
+        project: "example.com".to_string(),
+        version: Version::parse("1.2.3").unwrap(),
+    };
+    let bottle = bottle(&pkg).await;
+    let digest = format!("{:x}", Sha256::digest(&bottle));
+
+    let (platform, arch) = crate::types::host();
+    let path = format!("/{}/{}/{}/v1.2.3.tar.xz", pkg.project, platform, arch);
+    let sidecar = format!("{}  v1.2.3.tar.xz\n", digest).into_bytes();
+    let routes = HashMap::from([(format!("{}.sha256sum", path), sidecar), (path, bottle)]);
+
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config(serve(routes).await.url, tmp.path().join("pkgx"));
+
+    let lockfile = Lockfile::generate(std::slice::from_ref(&pkg), &config)
+        .await
+        .unwrap();
+    assert_eq!(lockfile.pkgs[0].sha256, digest);
+
+    let path = tmp.path().join("pkgx.lock");
+    lockfile.write(&path).unwrap();
+    let lockfile = Lockfile::read(&path, &config.platform).unwrap();
+    assert_eq!(lockfile.reqs().unwrap()[0].to_string(), "example.com=1.2.3");
+
+    let (installed, pending) = lockfile.partition(&config);
+    assert!(installed.is_empty());
+
+    let installations = install_multi_locked(&pending, &config, None::<Arc<NoProgress>>)
+        .await
+        .unwrap();
+    assert!(installations[0].path.join("bin/hi").is_file());
+
+    let (installed, pending) = lockfile.partition(&config);
+    assert_eq!(installed.len(), 1);
+    assert!(pending.is_empty());
+
+    // relocking takes what install verified rather than fetching it again
+    let mut config = config;
+    config.offline = true;
+    let relocked = Lockfile::from_installations(&installed, &config)
+        .await
+        .unwrap();
+    assert_eq!(relocked.pkgs[0].sha256, digest);
+    assert_eq!(relocked.pkgs[0].url, lockfile.pkgs[0].url);
+}
+
+#[test]
+fn test_lockfile_subset() {
+    let locked = |project: &str| LockedPackage {
+        project: project.to_string(),
+        version: Version::parse("1.0.0").unwrap(),
+        url: format!("https://example.com/{}.tar.xz", project),
+        sha256: "abc".to_string(),
+    };
+    let lockfile = Lockfile {
+        version: 1,
+        platform: "linux/x86-64".to_string(),
+        pkgs: ["a.com", "b.com", "c.com", "d.com"].map(locked).to_vec(),
+    };
+    let deps = |project: &str| {
+        Ok(match project {
+            "a.com" => vec![PackageReq::parse("b.com^1").unwrap()],
+            "b.com" => vec![PackageReq::parse("c.com^1").unwrap()],
+            "d.com" => vec![PackageReq::parse("e.com^1").unwrap()],
+            _ => vec![],
+        })
+    };
+
+    let subset = lockfile.subset(&["a.com".to_string()], deps).unwrap();
+    let projects: Vec<_> = subset.pkgs.iter().map(|locked| &locked.project).collect();
+    assert_eq!(projects, ["a.com", "b.com", "c.com"]);
+
+    let err = lockfile.subset(&["d.com".to_string()], deps).unwrap_err();
+    assert!(matches!(err, Error::Lockfile(msg) if msg.starts_with("e.com is not in")));
+}
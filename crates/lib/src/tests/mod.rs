@@ -0,0 +1,187 @@
+mod env;
+mod gc;
+mod hydrate;
+mod install;
+mod inventory;
+mod lockfile;
+mod moustache;
+mod pantry;
+mod pantry_db;
+mod resolve;
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use sha2::{Digest, Sha256};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+use crate::{
+    config::Config,
+    types::{Package, Platform},
+};
+
+pub(crate) struct Server {
+    pub url: String,
+    /// every request served as `(path, status)`
+    pub log: Arc<Mutex<Vec<(String, u16)>>>,
+}
+
+/// A minimal HTTP/1.1 stand-in for the dist server that supports `ETag`s and `Range`s.
+pub(crate) async fn serve(routes: HashMap<String, Vec<u8>>) -> Server {
+    serve_flaky(routes, 0).await
+}
+
+/// Like `serve` but the first `drops` bottle downloads are cut off halfway.
+pub(crate) async fn serve_flaky(routes: HashMap<String, Vec<u8>>, drops: usize) -> Server {
+    let drops = Arc::new(Mutex::new(drops));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let routes = Arc::new(routes);
+    let log = Arc::new(Mutex::new(vec![]));
+
+    tokio::spawn({
+        let log = log.clone();
+        async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let routes = routes.clone();
+                let log = log.clone();
+                let drops = drops.clone();
+                tokio::spawn(async move {
+                    let mut head = Vec::new();
+                    let mut buf = [0u8; 1024];
+                    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match socket.read(&mut buf).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => head.extend_from_slice(&buf[..n]),
+                        }
+                    }
+                    let head = String::from_utf8_lossy(&head).to_string();
+                    let path = head.split_whitespace().nth(1).unwrap_or("/").to_string();
+                    let header = |name: &str| {
+                        head.lines().find_map(|line| {
+                            let (key, value) = line.split_once(':')?;
+                            key.eq_ignore_ascii_case(name)
+                                .then(|| value.trim().to_string())
+                        })
+                    };
+                    let range_start = header("range").and_then(|range| {
+                        range
+                            .strip_prefix("bytes=")?
+                            .strip_suffix('-')?
+                            .parse()
+                            .ok()
+                    });
+
+                    let (status, etag, body) = match routes.get(&path) {
+                        Some(body) => {
+                            let etag = format!("\"{:x}\"", Sha256::digest(body));
+                            if header("if-none-match").as_ref() == Some(&etag) {
+                                (304, Some(etag), vec![])
+                            } else if let Some(start) = range_start {
+                                (206, Some(etag), body[start..].to_vec())
+                            } else {
+                                (200, Some(etag), body.clone())
+                            }
+                        }
+                        None => (404, None, vec![]),
+                    };
+                    let drop = path.ends_with(".tar.xz") && {
+                        let mut drops = drops.lock().unwrap();
+                        let drop = *drops > 0;
+                        *drops = drops.saturating_sub(1);
+                        drop
+                    };
+                    log.lock().unwrap().push((path, status));
+
+                    let mut rsp = format!(
+                        "HTTP/1.1 {} {}\r\ncontent-length: {}\r\nconnection: close\r\n",
+                        status,
+                        reason(status),
+                        body.len()
+                    );
+                    if let Some(etag) = etag {
+                        rsp.push_str(&format!("etag: {}\r\n", etag));
+                    }
+                    rsp.push_str("\r\n");
+                    let _ = socket.write_all(rsp.as_bytes()).await;
+                    if drop {
+                        let _ = socket.write_all(&body[..body.len() / 2]).await;
+                    } else {
+                        let _ = socket.write_all(&body).await;
+                    }
+                });
+            }
+        }
+    });
+
+    Server {
+        url: format!("http://{}", addr),
+        log,
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        206 => "Partial Content",
+        304 => "Not Modified",
+        _ => "Not Found",
+    }
+}
+
+pub(crate) fn config(dist_url: String, pkgx_dir: PathBuf) -> Config {
+    Config {
+        pantry_dir: pkgx_dir.join("pantry"),
+        pantry_overlays: vec![],
+        pantry_db_file: pkgx_dir.join("pantry.db"),
+        dist_urls: vec![dist_url],
+        pkgx_dir,
+        offline: false,
+        inventory_ttl: Duration::from_secs(3600),
+        download_concurrency: 8,
+        platform: Platform::current(),
+        env_paths: HashMap::new(),
+        warnings: vec![],
+    }
+}
+
+pub(crate) fn write_package(config: &Config, project: &str, yaml: &str) {
+    let dir = config.pantry_dir.join("projects").join(project);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("package.yml"), yaml).unwrap();
+}
+
+/// Builds a `.tar.xz` bottle laid out the way dist.pkgx.dev serves them.
+pub(crate) async fn bottle(pkg: &Package) -> Vec<u8> {
+    let mut builder = tokio_tar::Builder::new(Vec::new());
+    let data = b"#!/bin/sh\necho hi\n";
+    let mut header = tokio_tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o755);
+    header.set_cksum();
+    builder
+        .append_data(
+            &mut header,
+            format!("{}/v{}/bin/hi", pkg.project, pkg.version.raw),
+            &data[..],
+        )
+        .await
+        .unwrap();
+    let tarball = builder.into_inner().await.unwrap();
+
+    let mut xz = Vec::new();
+    async_compression::tokio::bufread::XzEncoder::new(&tarball[..])
+        .read_to_end(&mut xz)
+        .await
+        .unwrap();
+    xz
+}
@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+
+use crate::{
+    error::Error,
+    moustache::render,
+    types::{Installation, Package},
+    Version,
+};
+
+use super::config;
+
+fn installation(project: &str, version: &str) -> Installation {
+    Installation {
+        path: PathBuf::from(format!("/pkgx/{}/v{}", project, version)),
+        pkg: Package {
+            project: project.to_string(),
+            version: Version::parse(version).unwrap(),
+        },
+    }
+}
+
+#[test]
+fn test_render() {
+    let config = config("http://localhost".to_string(), PathBuf::from("/pkgx"));
+    let pkg = installation("python.org", "3.11.4+2");
+    let deps = vec![pkg.clone(), installation("openssl.org", "1.1.1w")];
+
+    let render = |template: &str| render(template, &pkg, &deps, &config);
+
+    assert_eq!(
+        render("{{prefix}}/bin").unwrap(),
+        "/pkgx/python.org/v3.11.4+2/bin"
+    );
+    assert_eq!(
+        render("python{{ version.marketing }}+{{version.build}}").unwrap(),
+        "python3.11+2"
+    );
+    assert_eq!(
+        render("${{deps.openssl.org.prefix}}/ssl:{{deps.openssl.org.version.raw}}").unwrap(),
+        "/pkgx/openssl.org/v1.1.1w/ssl:1.1.1w"
+    );
+    assert_eq!(
+        render("{{hw.platform}}/{{hw.arch}}").unwrap(),
+        config.platform.to_string()
+    );
+    assert_eq!(render("{{pkgx.prefix}}").unwrap(), "/pkgx");
+    assert_eq!(render("no moustaches, $5").unwrap(), "no moustaches, $5");
+
+    assert!(matches!(render("{{prefx}}"), Err(Error::Parse(_))));
+    assert!(matches!(
+        render("{{deps.zlib.net.prefix}}"),
+        Err(Error::Parse(_))
+    ));
+    assert!(matches!(render("{{prefix"), Err(Error::Parse(_))));
+}
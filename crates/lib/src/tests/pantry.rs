@@ -0,0 +1,152 @@
+use super::{config, write_package};
+use crate::{
+    pantry::{self, PantryEntry},
+    types::Platform,
+};
+
+#[test]
+fn test_lint() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+    write_package(
+        &config,
+        "a.com",
+        "dependencies:\n  c.com: ^1\n  darwin:\n    d.com: 2\nprovides:\n  - bin/a\n",
+    );
+    write_package(&config, "b.com", "dependencies:\n  c.com: [1]\n");
+    write_package(
+        &config,
+        "c.com",
+        "provides:\n  - bin/c\ncompanions:\n  linux:\n    d.com: '!1'\n",
+    );
+    write_package(&config, "d.com", "runtime:\n  env:\n    FOO: {a: b}\n");
+    write_package(&config, "e.com", "provides: [\n");
+
+    let issues: Vec<_> = pantry::lint(&config)
+        .iter()
+        .map(|issue| {
+            let path = issue.path.strip_prefix(config.pantry_dir.join("projects"));
+            format!(
+                "{}: {:?}:{:?}: {}",
+                path.unwrap().display(),
+                issue.line,
+                issue.column,
+                issue.reason
+            )
+        })
+        .collect();
+    assert_eq!(
+        issues,
+        [
+            "b.com/package.yml: Some(2):Some(10): dependencies.c.com: invalid type: sequence, expected a version constraint",
+            "c.com/package.yml: Some(5):Some(12): companions.linux.d.com: invalid version constraint `!1`",
+            "d.com/package.yml: Some(3):Some(10): runtime.env.FOO: invalid type: map, expected a string, number or bool",
+            "e.com/package.yml: Some(2):Some(1): did not find expected node content, while parsing a flow node",
+        ]
+    );
+}
+
+#[test]
+fn test_entry_for_platform() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+    write_package(
+        &config,
+        "a.com",
+        "dependencies:
+  c.com: ^1
+  darwin:
+    d.com: ^2
+provides:
+  darwin: [bin/a, bin/a-mac]
+  linux: [bin/a]
+runtime:
+  env:
+    FOO: bar
+    aarch64:
+      ARM: 1
+",
+    );
+    let file = pantry::files(&config).next().unwrap();
+
+    let entry = |platform: &str| PantryEntry::from_file(&file, &platform.parse().unwrap()).unwrap();
+    let deps = |entry: &PantryEntry| {
+        let mut deps: Vec<_> = entry.deps.iter().map(|dep| dep.to_string()).collect();
+        deps.sort();
+        deps
+    };
+
+    let darwin = entry("darwin/aarch64");
+    assert_eq!(deps(&darwin), ["c.com^1", "d.com^2"]);
+    assert_eq!(darwin.programs, ["bin/a", "bin/a-mac"]);
+    assert_eq!(darwin.env.get("ARM").map(String::as_str), Some("1"));
+
+    let linux = entry("linux/x86-64");
+    assert_eq!(deps(&linux), ["c.com^1"]);
+    assert_eq!(linux.programs, ["bin/a"]);
+    assert_eq!(linux.env.get("FOO").map(String::as_str), Some("bar"));
+    assert_eq!(linux.env.get("ARM"), None);
+
+    assert!("linux".parse::<Platform>().is_err());
+}
+
+#[test]
+fn test_entry_skips_what_lint_reports() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+    write_package(
+        &config,
+        "a.com",
+        "dependencies:
+  c.com: ^1
+  d.com: '!1'
+  linux/x86-64:
+    e.com: ^2
+provides:
+  linux: [bin/a]
+  freebsd: [bin/a]
+runtime:
+  env:
+    FOO: bar
+    UNSET: ~
+    MAP: {a: b}
+    linux:
+      BAZ: 1
+      QUX: [1]
+description: [a, b]
+",
+    );
+    let file = pantry::files(&config).next().unwrap();
+
+    let entry = PantryEntry::from_file(&file, &"linux/x86-64".parse().unwrap()).unwrap();
+    let deps: Vec<_> = entry.deps.iter().map(|dep| dep.to_string()).collect();
+    assert_eq!(deps, ["c.com^1"]);
+    assert_eq!(entry.programs, ["bin/a"]);
+    let mut env: Vec<_> = entry.env.iter().collect();
+    env.sort();
+    assert_eq!(
+        env,
+        [
+            (&"BAZ".to_string(), &"1".to_string()),
+            (&"FOO".to_string(), &"bar".to_string())
+        ]
+    );
+    assert_eq!(entry.description, None);
+
+    let issues: Vec<_> = pantry::lint(&config)
+        .iter()
+        .map(|issue| format!("{:?}:{:?}: {}", issue.line, issue.column, issue.reason))
+        .collect();
+    assert_eq!(
+        issues,
+        [
+            "Some(3):Some(10): dependencies.d.com: invalid version constraint `!1`",
+            "Some(5):Some(5): dependencies.linux/x86-64: invalid type: map, expected a version constraint",
+            "Some(8):Some(12): provides.freebsd: unknown variant `freebsd`, expected one of `linux`, `darwin`, `windows`",
+            "Some(12):Some(12): runtime.env.UNSET: invalid type: unit value, expected a string, number or bool",
+            "Some(13):Some(10): runtime.env.MAP: invalid type: map, expected a string, number or bool",
+            "Some(16):Some(12): runtime.env.linux.QUX: invalid type: sequence, expected a string, number or bool",
+            "Some(17):Some(14): description: invalid type: sequence, expected a string",
+        ]
+    );
+}
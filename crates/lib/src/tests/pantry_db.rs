@@ -0,0 +1,259 @@
+use std::fs;
+
+use rusqlite::Connection;
+
+use super::{config, write_package};
+use crate::{pantry_db, types::Platform};
+
+fn deps(project: &str, conn: &Connection) -> Vec<String> {
+    let mut deps: Vec<_> = pantry_db::deps_for_project(&project.to_string(), conn)
+        .unwrap()
+        .iter()
+        .map(|req| req.to_string())
+        .collect();
+    deps.sort();
+    deps
+}
+
+#[test]
+fn test_cache_only_reparses_changes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+    write_package(&config, "a.com", "dependencies:\n  c.com: ^1\n");
+    write_package(&config, "b.com", "dependencies:\n  c.com: ^2\n");
+
+    let mut conn = Connection::open(&config.pantry_db_file).unwrap();
+    pantry_db::cache(&config, &mut conn).unwrap();
+    assert_eq!(deps("a.com", &conn), ["c.com^1"]);
+    assert_eq!(deps("b.com", &conn), ["c.com^2"]);
+
+    // a row that only survives if a.com is copied rather than re-parsed
+    conn.execute(
+        "INSERT INTO dependencies (project, pkgspec) VALUES ('a.com', 'd.com^1')",
+        [],
+    )
+    .unwrap();
+    write_package(&config, "b.com", "dependencies:\n  c.com: ^3\n");
+
+    // a reader that opened the db before the rebuild keeps a consistent view
+    let reader = Connection::open(&config.pantry_db_file).unwrap();
+
+    pantry_db::cache(&config, &mut conn).unwrap();
+    assert_eq!(deps("a.com", &conn), ["c.com^1", "d.com^1"]);
+    assert_eq!(deps("b.com", &conn), ["c.com^3"]);
+
+    #[cfg(unix)]
+    assert_eq!(deps("b.com", &reader), ["c.com^2"]);
+    drop(reader);
+
+    let leftovers: Vec<_> = fs::read_dir(tmp.path())
+        .unwrap()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty());
+}
+
+#[test]
+fn test_schema_versions() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+    write_package(&config, "a.com", "dependencies:\n  c.com: ^1\n");
+
+    // a db from before we versioned the schema is rebuilt
+    let mut conn = Connection::open(&config.pantry_db_file).unwrap();
+    conn.execute_batch("CREATE TABLE dependencies (project TEXT, pkgspec TEXT);")
+        .unwrap();
+    drop(conn);
+    conn = pantry_db::open(&config.pantry_db_file).unwrap();
+    assert!(pantry_db::needs_rebuild(&conn).unwrap());
+
+    pantry_db::cache(&config, &mut conn).unwrap();
+    assert!(!pantry_db::needs_rebuild(&conn).unwrap());
+    assert_eq!(deps("a.com", &conn), ["c.com^1"]);
+    let version: i64 = conn
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .unwrap();
+    assert_eq!(version, pantry_db::SCHEMA_VERSION);
+
+    // a newer pkgx’s db is used as is if it says we can read it
+    conn.pragma_update(None, "user_version", pantry_db::SCHEMA_VERSION + 1)
+        .unwrap();
+    assert!(!pantry_db::needs_rebuild(&conn).unwrap());
+    conn.execute(
+        "UPDATE meta SET value = ?1 WHERE key = 'readable_by'",
+        [(pantry_db::SCHEMA_VERSION + 1).to_string()],
+    )
+    .unwrap();
+    assert!(pantry_db::needs_rebuild(&conn).unwrap());
+
+    pantry_db::cache(&config, &mut conn).unwrap();
+    assert!(!pantry_db::needs_rebuild(&conn).unwrap());
+    assert_eq!(deps("a.com", &conn), ["c.com^1"]);
+}
+
+#[test]
+fn test_overlays() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+    write_package(&config, "a.com", "dependencies:\n  c.com: ^1\n");
+    write_package(&config, "b.com", "dependencies:\n  c.com: ^1\n");
+
+    let base = config.pantry_dir.clone();
+    let overlay = tmp.path().join("overlay");
+    config.pantry_dir = overlay.clone();
+    write_package(&config, "a.com", "dependencies:\n  c.com: ^2\n");
+    write_package(&config, "b.com", "dependencies:\n  c.com: ^1\n");
+    write_package(&config, "d.com", "dependencies:\n  c.com: ^3\n");
+    config.pantry_dir = base.clone();
+    config.pantry_overlays = vec![overlay.clone()];
+
+    let mut conn = pantry_db::open(&config.pantry_db_file).unwrap();
+    pantry_db::cache(&config, &mut conn).unwrap();
+    assert_eq!(deps("a.com", &conn), ["c.com^2"]);
+    assert_eq!(deps("d.com", &conn), ["c.com^3"]);
+    for project in ["a.com", "b.com", "d.com"] {
+        let pantry = pantry_db::pantry_for_project(&project.to_string(), &conn);
+        assert_eq!(pantry.unwrap(), Some(overlay.clone()));
+    }
+
+    // b.com is unchanged but now comes from the base pantry
+    fs::remove_dir_all(overlay.join("projects/a.com")).unwrap();
+    fs::remove_dir_all(overlay.join("projects/b.com")).unwrap();
+    pantry_db::cache(&config, &mut conn).unwrap();
+    assert_eq!(deps("a.com", &conn), ["c.com^1"]);
+    assert_eq!(deps("b.com", &conn), ["c.com^1"]);
+    for (project, pantry) in [("a.com", &base), ("b.com", &base), ("d.com", &overlay)] {
+        let found = pantry_db::pantry_for_project(&project.to_string(), &conn);
+        assert_eq!(found.unwrap().as_ref(), Some(pantry));
+    }
+}
+
+#[test]
+fn test_info() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+    write_package(
+        &config,
+        "a.com",
+        "display-name: A
+description: does a things
+homepage: https://a.com
+license: [MIT, Apache-2.0]
+platforms: darwin
+provides:
+  - bin/a
+dependencies:
+  c.com: ^1
+build:
+  dependencies:
+    b.com: '*'
+  script: make
+",
+    );
+    write_package(&config, "b.com", "build: make\n");
+
+    let mut conn = pantry_db::open(&config.pantry_db_file).unwrap();
+    pantry_db::cache(&config, &mut conn).unwrap();
+
+    let info = pantry_db::info(&"a.com".to_string(), &conn)
+        .unwrap()
+        .unwrap();
+    assert_eq!(info.display_name.as_deref(), Some("A"));
+    assert_eq!(info.description.as_deref(), Some("does a things"));
+    assert_eq!(info.homepage.as_deref(), Some("https://a.com"));
+    assert_eq!(info.license.as_deref(), Some("MIT OR Apache-2.0"));
+    assert_eq!(info.platforms, ["darwin"]);
+    assert_eq!(info.programs, ["a"]);
+    assert_eq!(info.dependencies, ["c.com^1"]);
+    assert_eq!(info.build_dependencies, ["b.com"]);
+    assert_eq!(info.pantry, Some(config.pantry_dir.clone()));
+
+    let info = pantry_db::info(&"b.com".to_string(), &conn)
+        .unwrap()
+        .unwrap();
+    assert!(info.build_dependencies.is_empty());
+    assert!(pantry_db::info(&"c.com".to_string(), &conn)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn test_search() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+    write_package(
+        &config,
+        "github.com/mikefarah/yq",
+        "provides:\n  - bin/yq\ndescription: a portable command-line YAML processor\n",
+    );
+    write_package(
+        &config,
+        "pyyaml.org",
+        "display-name: PyYAML\ndescription: YAML parser for Python\n",
+    );
+    write_package(
+        &config,
+        "nodejs.org",
+        "provides:\n  - bin/node\n  - bin/npm\ndescription: JavaScript runtime\n",
+    );
+    write_package(&config, "python.org", "provides:\n  - bin/python3\n");
+
+    let mut conn = pantry_db::open(&config.pantry_db_file).unwrap();
+    pantry_db::cache(&config, &mut conn).unwrap();
+
+    let search = |query| -> Vec<String> {
+        pantry_db::search(query, 10, &conn)
+            .unwrap()
+            .into_iter()
+            .map(|hit| hit.project)
+            .collect()
+    };
+    // a match in the name outranks one in the description
+    assert_eq!(search("python"), ["python.org", "pyyaml.org"]);
+    assert_eq!(search("pyyaml"), ["pyyaml.org"]);
+    assert_eq!(search("yaml").len(), 2);
+    assert_eq!(search("Node"), ["nodejs.org"]);
+    assert_eq!(search("javasc"), ["nodejs.org"]);
+    assert_eq!(search("yaml python"), ["pyyaml.org"]);
+    assert!(search("\"*").is_empty());
+
+    assert_eq!(pantry_db::suggestions("nod", &conn).unwrap(), ["node"]);
+    assert_eq!(pantry_db::suggestions("nmp", &conn).unwrap(), ["npm"]);
+    assert_eq!(pantry_db::suggestions("pyyml", &conn).unwrap(), ["PyYAML"]);
+    assert!(pantry_db::suggestions("rustc", &conn).unwrap().is_empty());
+}
+
+#[test]
+fn test_supported() {
+    let tmp = tempfile::tempdir().unwrap();
+    let config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+    write_package(&config, "any.com", "provides:\n  - bin/any\n");
+    write_package(&config, "linux.com", "platforms: linux\n");
+    write_package(
+        &config,
+        "arm.com",
+        "platforms: [darwin/aarch64, linux/aarch64]\n",
+    );
+
+    let mut conn = pantry_db::open(&config.pantry_db_file).unwrap();
+    pantry_db::cache(&config, &mut conn).unwrap();
+
+    let linux_x86: Platform = "linux/x86-64".parse().unwrap();
+    let darwin_arm: Platform = "darwin/aarch64".parse().unwrap();
+    let supported = |project, platform| pantry_db::supported(project, platform, &conn).unwrap();
+
+    assert!(supported("any.com", &linux_x86));
+    assert!(supported("any.com", &darwin_arm));
+    assert!(supported("linux.com", &linux_x86));
+    assert!(!supported("linux.com", &darwin_arm));
+    assert!(!supported("arm.com", &linux_x86));
+    assert!(supported("arm.com", &darwin_arm));
+
+    assert_eq!(
+        pantry_db::ensure_supported("arm.com", &linux_x86, &conn)
+            .unwrap_err()
+            .to_string(),
+        "arm.com is not available for linux/x86-64"
+    );
+}
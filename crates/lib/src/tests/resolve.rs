@@ -0,0 +1,31 @@
+use std::fs;
+
+use super::config;
+use crate::{error::Error, hydrate::Policy, resolve::solve, types::PackageReq};
+
+#[tokio::test]
+async fn test_offline_solve_reports_everything_missing() {
+    let tmp = tempfile::tempdir().unwrap();
+    let mut config = config("http://127.0.0.1:1".to_string(), tmp.path().to_path_buf());
+    config.offline = true;
+    fs::create_dir_all(tmp.path().join("a.com/v1.0.0")).unwrap();
+
+    let reqs = vec![
+        PackageReq::parse("a.com").unwrap(),
+        PackageReq::parse("c.com^2").unwrap(),
+    ];
+    let err = solve(&reqs, &Policy::default(), &config, |pkg| {
+        Ok(match pkg.project.as_str() {
+            "a.com" => vec![PackageReq::parse("b.com^1").unwrap()],
+            _ => vec![],
+        })
+    })
+    .await
+    .unwrap_err();
+
+    let Error::Offline(mut missing) = err else {
+        panic!("expected offline, got {:?}", err);
+    };
+    missing.sort();
+    assert_eq!(missing, ["b.com^1", "c.com^2"]);
+}
@@ -1,9 +1,9 @@
+use crate::error::Error;
 use lazy_static::lazy_static;
 use libsemverator::range::Range as VersionReq;
 use libsemverator::semver::Semver as Version;
 use serde::ser::SerializeStruct;
 use serde::{Serialize, Serializer};
-use std::error::Error;
 use std::fmt;
 
 //TODO regex is probs not most efficient (but do perf tests if you change it)
@@ -32,11 +32,11 @@ pub struct PackageReq {
 use regex::Regex;
 
 impl PackageReq {
-    pub fn parse(pkgspec: &str) -> Result<Self, Box<dyn Error>> {
+    pub fn parse(pkgspec: &str) -> Result<Self, Error> {
         let input = pkgspec.trim();
         let captures = PACKAGE_REGEX
             .captures(input)
-            .ok_or_else(|| format!("invalid pkgspec: {}", input))?;
+            .ok_or_else(|| Error::Parse(format!("invalid pkgspec: {}", input)))?;
 
         let project = captures.get(1).unwrap().as_str().to_string();
         let str = if let Some(cap) = captures.get(2) {
@@ -87,21 +87,20 @@ impl Serialize for Installation {
     }
 }
 
-// These are only used per build at present
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Host {
     Darwin,
     Linux,
     Windows,
 }
 
-// These are only used per build at present
-#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Arch {
     Arm64,
     X86_64,
 }
 
+/// The platform we were built for.
 pub fn host() -> (Host, Arch) {
     #[cfg(target_os = "macos")]
     let host = Host::Darwin;
@@ -120,6 +119,57 @@ pub fn host() -> (Host, Arch) {
     (host, arch)
 }
 
+/// What we resolve and fetch bottles for, usually the platform we are
+/// running on. Displayed and parsed as eg. `linux/x86-64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Platform {
+    pub host: Host,
+    pub arch: Arch,
+}
+
+impl Platform {
+    pub fn current() -> Self {
+        let (host, arch) = host();
+        Self { host, arch }
+    }
+
+    /// Whether a `platforms:` entry from the pantry, eg. `darwin` or
+    /// `linux/aarch64`, includes us.
+    pub fn matches(&self, platform: &str) -> bool {
+        match platform.split_once('/') {
+            Some((host, arch)) => host == self.host.to_string() && arch == self.arch.to_string(),
+            None => platform == self.host.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.host, self.arch)
+    }
+}
+
+impl std::str::FromStr for Platform {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::Parse(format!("invalid platform `{}`, eg. linux/x86-64", s));
+        let (host, arch) = s.split_once('/').ok_or_else(invalid)?;
+        let host = match host {
+            "darwin" | "macos" => Host::Darwin,
+            "linux" => Host::Linux,
+            "windows" => Host::Windows,
+            _ => return Err(invalid()),
+        };
+        let arch = match arch {
+            "aarch64" | "arm64" => Arch::Arm64,
+            "x86-64" | "x86_64" | "amd64" => Arch::X86_64,
+            _ => return Err(invalid()),
+        };
+        Ok(Self { host, arch })
+    }
+}
+
 impl fmt::Display for Host {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let os_str = match self {
@@ -1,8 +1,9 @@
+use crate::error::Error;
 #[cfg(not(windows))]
 use std::os::unix::fs::PermissionsExt;
-use std::{error::Error, path::Path};
+use std::path::Path;
 
-pub async fn find_program(arg: &str, paths: &Vec<String>) -> Result<String, Box<dyn Error>> {
+pub async fn find_program(arg: &str, paths: &Vec<String>) -> Result<String, Error> {
     if Path::new(arg).is_absolute() {
         return Ok(arg.to_string());
     } else if arg.contains("/") {
@@ -32,5 +33,29 @@ pub async fn find_program(arg: &str, paths: &Vec<String>) -> Result<String, Box<
             }
         }
     }
-    Err(format!("cmd not found: {}", arg).into())
+    Err(Error::ProgramNotFound(arg.to_string()))
+}
+
+/// How many characters must be inserted, deleted, substituted or swapped with
+/// their neighbour to turn `a` into `b` (the optimal string alignment distance).
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    d[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
 }